@@ -0,0 +1,106 @@
+// network.rs
+//
+// Description:
+//     Benchmarks for building, signaling, routing and simulating
+//     large generated track networks.
+//
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use signaling::generator;
+use signaling::system::{create_system, System};
+
+const LINE_SIZES: [usize; 3] = [100, 500, 1000];
+const LADDER_SIZES: [usize; 3] = [25, 100, 250];
+const GRID_SIZES: [(usize, usize); 3] = [(2, 5), (4, 10), (8, 20)];
+
+// Builds a loop grid with signals on every segment end, and one
+// train per row running from the start of the row to its end.
+fn grid_with_trains(rows: usize, cols: usize) -> System {
+    let mut sys = create_system();
+    let ends = generator::build_loop_grid(&mut sys, rows, cols);
+    generator::place_all_signals(&mut sys);
+    for (first, last) in &ends {
+        generator::place_train(&mut sys, first, last);
+    }
+    sys
+}
+
+fn bench_connect_segments(c: &mut Criterion) {
+    let mut group = c.benchmark_group("connect_segments");
+    for size in LINE_SIZES {
+        group.bench_with_input(BenchmarkId::new("line", size), &size, |b, &size| {
+            b.iter(|| {
+                let mut sys = create_system();
+                generator::build_line(&mut sys, size);
+                sys
+            })
+        });
+    }
+    for rungs in LADDER_SIZES {
+        group.bench_with_input(BenchmarkId::new("ladder", rungs), &rungs, |b, &rungs| {
+            b.iter(|| {
+                let mut sys = create_system();
+                generator::build_ladder(&mut sys, rungs);
+                sys
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_update_signals(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_all_signals");
+    for (rows, cols) in GRID_SIZES {
+        let mut sys = grid_with_trains(rows, cols);
+        let id = BenchmarkId::new("grid", format!("{rows}x{cols}"));
+        group.bench_function(id, |b| b.iter(|| sys.update_all_signals()));
+    }
+    group.finish();
+}
+
+fn bench_route_planning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_route");
+    for size in LINE_SIZES {
+        let mut sys = create_system();
+        let line = generator::build_line(&mut sys, size);
+        let (first, last) = (&line[0], &line[size - 1]);
+        group.bench_function(BenchmarkId::new("line", size), |b| {
+            b.iter(|| sys.find_route(first, last))
+        });
+    }
+    for (rows, cols) in GRID_SIZES {
+        let mut sys = create_system();
+        let ends = generator::build_loop_grid(&mut sys, rows, cols);
+        let (first, last) = (&ends[0].0, &ends[rows - 1].1);
+        let id = BenchmarkId::new("grid", format!("{rows}x{cols}"));
+        group.bench_function(id, |b| b.iter(|| sys.find_route(first, last)));
+    }
+    group.finish();
+}
+
+fn bench_simulation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulation");
+    for (rows, cols) in GRID_SIZES {
+        let id = BenchmarkId::new("step", format!("{rows}x{cols}"));
+        group.bench_function(id, |b| {
+            b.iter_batched_ref(|| grid_with_trains(rows, cols),
+                               |sys| sys.step(),
+                               BatchSize::LargeInput)
+        });
+        let id = BenchmarkId::new("run", format!("{rows}x{cols}"));
+        group.bench_function(id, |b| {
+            b.iter_batched_ref(|| grid_with_trains(rows, cols),
+                               |sys| sys.run(),
+                               BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches,
+                 bench_connect_segments,
+                 bench_update_signals,
+                 bench_route_planning,
+                 bench_simulation);
+criterion_main!(benches);
@@ -12,7 +12,7 @@ pub enum NodeType {
 
 // A junction switch can be in one of three possible states.
 //
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum JSwitch {
     JSwitchNone,    // Unknown or possibly in motion.
     JSwitchLeft,    // Switch from common to left track.
@@ -41,12 +41,29 @@ pub const END_A: End = 0;
 pub const END_B: End = 1;
 pub const NUM_ENDS: End = 2;
 
+// Returns the end at the other side of an edge.
+//
+pub fn opposite_end(end: End) -> End {
+    assert!(end == END_A || end == END_B);
+    if end == END_A { END_B } else { END_A }
+}
+
 pub struct NodeSlot {
     pub ns_node: String,
     pub ns_slot: Slot,
 }
 
+#[derive(PartialEq, Clone)]
 pub struct EdgeEnd {
     pub ee_edge: String,
     pub ee_end:  End,
-}
\ No newline at end of file
+}
+
+// A position on the track schematic. X runs along the track in units
+// of segment length, and Y counts rows downward.
+//
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
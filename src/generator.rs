@@ -0,0 +1,158 @@
+// generator.rs
+//
+// Description:
+//     Builds synthetic track networks through the System API. These
+//     are much larger than the hand drawn sample networks, and are
+//     used to measure how the system scales.
+//
+
+pub mod random;
+
+use crate::common::*;
+use crate::system::System;
+
+use itertools::Itertools;
+
+// Adds a new track segment and returns its name.
+//
+fn add_segment(sys: &mut System) -> String {
+    match sys.create_edge("") {
+        Some(e) => e.name.clone(),
+        None => panic!("Failed to create a track segment"),
+    }
+}
+
+// Connects end `end1` of `edge1` to end `end2` of `edge2`.
+//
+fn connect(sys: &mut System, edge1: &str, end1: End, edge2: &str, end2: End) {
+    let s1 = EdgeEnd { ee_edge: String::from(edge1), ee_end: end1 };
+    let s2 = EdgeEnd { ee_edge: String::from(edge2), ee_end: end2 };
+    let rc = sys.connect_segments(&s1, &s2);
+    assert!(rc == 0, "Failed to connect {edge1} to {edge2}");
+}
+
+// ==================================================================
+// build_line
+// ==================================================================
+// Builds a single line of `segments` track segments, each one
+// connected from its B end to the A end of the next. Returns the
+// segment names in order along the line.
+pub fn build_line(sys: &mut System, segments: usize) -> Vec<String> {
+    let mut line: Vec<String> = vec![];
+    for _ in 0..segments {
+        let name = add_segment(sys);
+        if let Some(prev) = line.last() {
+            connect(sys, prev, END_B, &name, END_A);
+        }
+        line.push(name);
+    }
+    line
+}
+
+// ==================================================================
+// build_ladder
+// ==================================================================
+// Builds two parallel lines joined by `rungs` crossovers. Crossover
+// i leaves the upper line at a junction after upper segment 2i+1 and
+// joins the lower line at a junction before lower segment 2i+2, so a
+// train on the upper line can cross over to the lower line. Returns
+// the upper and lower segment names.
+pub fn build_ladder(sys: &mut System, rungs: usize) -> (Vec<String>, Vec<String>) {
+    let upper = build_line(sys, 2 * rungs + 2);
+    let lower = build_line(sys, 2 * rungs + 2);
+    for ix in 0..rungs {
+        let rung = add_segment(sys);
+        // The upper segment becomes the common edge of the junction,
+        // with the line ahead on the left and the rung on the right.
+        connect(sys, &upper[2 * ix + 1], END_B, &rung, END_A);
+        connect(sys, &lower[2 * ix + 2], END_A, &rung, END_B);
+    }
+    (upper, lower)
+}
+
+// ==================================================================
+// build_loop_grid
+// ==================================================================
+// Builds `rows` lines, each made of `cols` passing loops, where a
+// junction splits the line into two parallel tracks that rejoin at
+// a second junction. Neighboring rows are joined by a crossover in
+// the link between each pair of loops. Returns the names of the
+// first and last segment of each row.
+pub fn build_loop_grid(sys: &mut System, rows: usize, cols: usize)
+    -> Vec<(String, String)>
+{
+    // links[row][col] holds the three segments leading into loop
+    // `col` of `row`. The middle nodes of the link are continuations
+    // that can branch off to the row below and the row above.
+    let mut links: Vec<Vec<Vec<String>>> = vec![];
+    let mut ends = vec![];
+    for _ in 0..rows {
+        let mut row_links = vec![];
+        let mut last = String::new();
+        for col in 0..=cols {
+            let link = build_line(sys, 3);
+            if col > 0 {
+                connect(sys, &link[0], END_A, &last, END_B);
+            }
+            if col < cols {
+                let upper = add_segment(sys);
+                let lower = add_segment(sys);
+                connect(sys, &link[2], END_B, &upper, END_A);
+                connect(sys, &link[2], END_B, &lower, END_A);
+                last = add_segment(sys);
+                connect(sys, &last, END_A, &upper, END_B);
+                connect(sys, &last, END_A, &lower, END_B);
+            }
+            row_links.push(link);
+        }
+        let first = row_links[0][0].clone();
+        let final_seg = row_links[cols][2].clone();
+        ends.push((first, final_seg));
+        links.push(row_links);
+    }
+    for (above, below) in links.iter().tuple_windows() {
+        for (link1, link2) in above.iter().zip(below) {
+            let rung = add_segment(sys);
+            connect(sys, &link1[0], END_B, &rung, END_A);
+            connect(sys, &link2[2], END_A, &rung, END_B);
+        }
+    }
+    ends
+}
+
+// ==================================================================
+// place_all_signals
+// ==================================================================
+// Places a signal light on both ends of every track segment, and
+// brings the signal aspects up to date.
+pub fn place_all_signals(sys: &mut System) {
+    for name in sys.get_all_edges() {
+        if let Some(edge) = sys.get_edge_mut(&name) {
+            for end in [END_A, END_B] {
+                if edge.signals[end].edge.ee_edge.is_empty() {
+                    edge.place_signal_light(end);
+                }
+            }
+        }
+    }
+    sys.update_all_signals();
+}
+
+// ==================================================================
+// place_train
+// ==================================================================
+// Creates a train on the start segment bound for the destination
+// segment and plans its route. Returns the train name, or None if
+// the start segment is occupied or there is no route (in which case
+// the train is left standing on the start segment).
+pub fn place_train(sys: &mut System, start: &str, dest: &str) -> Option<String> {
+    match sys.get_edge(&String::from(start)) {
+        None => return None,
+        Some(e) => if !e.train.is_empty() { return None; },
+    }
+    let name = sys.create_train("")?.name.clone();
+    if sys.place_train(&name, &String::from(start), &String::from(dest)) != 0 {
+        return None;
+    }
+    Some(name)
+}
@@ -0,0 +1,170 @@
+// random.rs
+//
+// Description:
+//     Generates random but valid track networks and train scenarios,
+//     for fuzzing and for training exercises. Networks are grown one
+//     segment at a time through connect_segments, so they obey the
+//     same slot rules as a network built by hand: a new segment may
+//     only be attached to a terminator (making a continuation) or to
+//     a continuation (making a junction). The generator is seeded, so
+//     the same seed and configuration always produce the same result.
+//
+
+use crate::common::*;
+use crate::system::{create_system, System};
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+pub struct RandomConfig {
+    // Number of track segments in the network.
+    pub segments: usize,
+    // Chance that a new segment branches off at a continuation,
+    // creating a junction, rather than extending a line.
+    pub junction_probability: f64,
+    // Chance that the far end of a new segment is joined back into
+    // the network, closing a loop.
+    pub loop_probability: f64,
+    // Chance of a signal light on any one segment end.
+    pub signal_probability: f64,
+    // Number of trains to place, each with its own origin and a
+    // reachable destination.
+    pub trains: usize,
+}
+
+impl Default for RandomConfig {
+    fn default() -> Self {
+        RandomConfig {
+            segments: 20,
+            junction_probability: 0.3,
+            loop_probability: 0.1,
+            signal_probability: 0.5,
+            trains: 3,
+        }
+    }
+}
+
+// Returns all segment ends whose node has room for another segment,
+// split into those at terminators and those at continuations.
+//
+fn open_ends(sys: &System) -> (Vec<EdgeEnd>, Vec<EdgeEnd>) {
+    let mut terminators = vec![];
+    let mut continuations = vec![];
+    for name in sys.get_all_edges() {
+        let edge = match sys.get_edge(&name) { Some(e) => e, None => continue };
+        for end in [END_A, END_B] {
+            let ee = EdgeEnd { ee_edge: name.clone(), ee_end: end };
+            match sys.get_node_ref(&edge.get_node(end).ns_node) {
+                Some(n) if n.get_node_type() == NodeType::Terminator => terminators.push(ee),
+                Some(n) if n.get_node_type() == NodeType::Continuation => continuations.push(ee),
+                _ => (),
+            }
+        }
+    }
+    (terminators, continuations)
+}
+
+// ==================================================================
+// build_random_network
+// ==================================================================
+// Grows a random connected network of `cfg.segments` segments.
+pub fn build_random_network(sys: &mut System, rng: &mut ChaCha8Rng, cfg: &RandomConfig) {
+    if cfg.segments == 0 { return; }
+    if sys.create_edge("").is_none() { panic!("Failed to create a track segment"); }
+
+    for _ in 1..cfg.segments {
+        let (terminators, continuations) = open_ends(sys);
+        let branch = !continuations.is_empty()
+            && (terminators.is_empty() || rng.gen_bool(cfg.junction_probability));
+        let attach = if branch { continuations.choose(rng) } else { terminators.choose(rng) };
+        let attach = match attach { Some(a) => a.clone(), None => break };
+
+        let name = match sys.create_edge("") {
+            Some(e) => e.name.clone(),
+            None => panic!("Failed to create a track segment"),
+        };
+        let new_a = EdgeEnd { ee_edge: name.clone(), ee_end: END_A };
+        let rc = sys.connect_segments(&attach, &new_a);
+        assert!(rc == 0, "Failed to attach {} to {}", name, attach.ee_edge);
+
+        if rng.gen_bool(cfg.loop_probability) {
+            // Join the far end back into the network, anywhere other
+            // than the new segment itself or the node it starts from.
+            let start_node = match sys.get_edge(&name) {
+                Some(e) => e.get_node(END_A).ns_node,
+                None => String::new(),
+            };
+            let (terminators, continuations) = open_ends(sys);
+            let targets: Vec<EdgeEnd> = terminators.into_iter().chain(continuations)
+                .filter(|ee| ee.ee_edge != name)
+                .filter(|ee| match sys.get_edge(&ee.ee_edge) {
+                    Some(e) => e.get_node(ee.ee_end).ns_node != start_node,
+                    None => false,
+                })
+                .collect();
+            if let Some(target) = targets.choose(rng) {
+                let new_b = EdgeEnd { ee_edge: name.clone(), ee_end: END_B };
+                let rc = sys.connect_segments(target, &new_b);
+                assert!(rc == 0, "Failed to close a loop at {}", name);
+            }
+        }
+    }
+}
+
+// ==================================================================
+// place_random_signals
+// ==================================================================
+// Places a signal on each segment end with the given probability.
+pub fn place_random_signals(sys: &mut System, rng: &mut ChaCha8Rng, probability: f64) {
+    for name in sys.get_all_edges() {
+        for end in [END_A, END_B] {
+            if !rng.gen_bool(probability) { continue; }
+            if let Some(edge) = sys.get_edge_mut(&name) {
+                if !edge.has_signal(end) {
+                    edge.place_signal_light(end);
+                }
+            }
+        }
+    }
+    sys.update_all_signals();
+}
+
+// ==================================================================
+// place_random_trains
+// ==================================================================
+// Places up to `count` trains, each on its own randomly chosen
+// segment with a randomly chosen destination that it can reach.
+// Returns the names of the trains placed.
+pub fn place_random_trains(sys: &mut System, rng: &mut ChaCha8Rng, count: usize) -> Vec<String> {
+    let mut starts = sys.get_all_edges();
+    starts.shuffle(rng);
+    let mut placed = vec![];
+    for start in starts {
+        if placed.len() == count { break; }
+        let dests: Vec<String> = sys.get_all_edges().into_iter()
+            .filter(|d| *d != start && sys.find_route(&start, d).is_some())
+            .collect();
+        if let Some(dest) = dests.choose(rng) {
+            if let Some(name) = super::place_train(sys, &start, dest) {
+                placed.push(name);
+            }
+        }
+    }
+    placed
+}
+
+// ==================================================================
+// generate_scenario
+// ==================================================================
+// Builds a complete random scenario -- network, signals and trains --
+// from a seed. The network can be written out in the track file
+// format with System::serialize.
+pub fn generate_scenario(seed: u64, cfg: &RandomConfig) -> System {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut sys = create_system();
+    build_random_network(&mut sys, &mut rng, cfg);
+    place_random_signals(&mut sys, &mut rng, cfg.signal_probability);
+    place_random_trains(&mut sys, &mut rng, cfg.trains);
+    sys
+}
@@ -0,0 +1,13 @@
+// lib.rs
+//
+// Description:
+//     Library interface for the railroad signaling case study. The
+//     interactive program, the benchmarks and the tests all build
+//     on the modules exported here.
+//
+pub mod version;
+pub mod common;
+pub mod system;
+pub mod generator;
+#[cfg(feature = "tui")]
+pub mod tui;
@@ -4,22 +4,34 @@
 // Description:
 //     Entry point for the railroad signaling case study implementation.
 //
-pub mod version;
-use version::SG_VERSION_MAJOR;
-use version::SG_VERSION_MINOR;
+use signaling::version::SG_VERSION_MAJOR;
+use signaling::version::SG_VERSION_MINOR;
 
-pub mod common;
+use signaling::common;
 use common::END_A;
 use common::END_B;
 use common::NUM_ENDS;
 use common::JSwitch::JSwitchLeft;
 
-pub mod system;
+use signaling::system;
 use system::System;
-use system::edge_count;
+use system::train::Waypoint;
+use system::faults::Fault;
+use system::advisor;
+use system::advisor::Movement;
 
+use std::fs;
+use std::fs::File;
 use std::io;
+use std::io::BufReader;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Seconds each tick is shown for in an animated replay.
+const REPLAY_TICK_SECS: f64 = 1.0;
+
+// Headway in ticks offered when advising on signal placement.
+const ADVISOR_HEADWAY: u32 = 3;
 
 fn enter_name() -> String
 {
@@ -36,13 +48,12 @@ fn enter_name() -> String
     resp
 }
 
-fn name_from_number(num: &String) -> String
+fn name_from_number(num: &str) -> String
 {
-    let unum : u32;
-    match num.trim().parse() {
-        Ok(n) => unum = n,
+    let unum : u32 = match num.trim().parse() {
+        Ok(n) => n,
         Err(_) => return String::new(),
-    }
+    };
     format!("tseg{:03}", unum)
 }
 
@@ -70,11 +81,11 @@ fn cmd_add_segment(sys: &mut System) -> i32 {
     if let Some(edge) = sys.create_edge("") {
 
         println!("Added new track segment \"{}\"", edge.name);
-        return 0;
+        0
     }
     else {
         println!("ERROR: Failed to add a new track segment");
-        return 1;
+        1
     }
 }
 fn cmd_connect_segments(sys: &mut System) -> i32 {
@@ -119,7 +130,7 @@ fn cmd_connect_segments(sys: &mut System) -> i32 {
     let rc = sys.connect_segments(&seg1, &seg2);
     if rc == 0 {
         if let Some(edge) = sys.get_edge(&edge1_name) {
-            edge.show(sys, NUM_ENDS);
+            print!("{}", edge.format(sys, NUM_ENDS));
         }
     }
     rc
@@ -129,20 +140,14 @@ fn cmd_place_signal(sys: &mut System) -> i32 {
     let mut resp1 = enter_name();
     if resp1.is_empty() { return 0; }
     let mut edge = sys.get_edge_mut(&resp1);
-    match edge {
-        None => {
-            let rnum = resp1;
-            resp1 = name_from_number(&rnum);
-            edge = sys.get_edge_mut(&resp1);
-            match edge {
-                None => {
-                    println!("No such segment \"{rnum}\"");
-                    return 1;
-                }
-                Some(_) => (),
-            }
+    if edge.is_none() {
+        let rnum = resp1;
+        resp1 = name_from_number(&rnum);
+        edge = sys.get_edge_mut(&resp1);
+        if edge.is_none() {
+            println!("No such segment \"{rnum}\"");
+            return 1;
         }
-        Some(_) => (),
     }
     let end1 = enter_a_or_b();
 
@@ -154,22 +159,19 @@ fn cmd_place_signal(sys: &mut System) -> i32 {
             // Get the immutable edge to show the final result.
             let shed = sys.get_edge(&resp1);
             if let Some(sref) = shed {
-                sref.show(sys, end1);
+                print!("{}", sref.format(sys, end1));
             }
         }
         return rc;
     }
     println!("ERROR: cmd_place_signal failed");
-    return 1;
+    1
 }
-fn cmd_toggle_switch(sys: &mut System) -> i32 {
-    let jctv = sys.get_all_junctions();
-    if jctv.is_empty() {
-        println!(">>> There are no junctions in the track network <<<");
-        return 0;
-    }
+// Lists the junctions and asks for one by number. Returns its index,
+// or None if the entry is not a junction number.
+fn enter_junction(jctv: &[String]) -> Option<usize> {
     let mut jnum = 0;
-    for name in &jctv {
+    for name in jctv {
         jnum += 1;
         println!("{jnum}: {name}");
     }
@@ -190,27 +192,210 @@ fn cmd_toggle_switch(sys: &mut System) -> i32 {
     }
     if (val < 1) || (val > jnum) {
         println!("No such junction");
-        return 1;
+        return None;
     }
     val -= 1; // Make the index zero based.
+    Some(val)
+}
+fn cmd_toggle_switch(sys: &mut System) -> i32 {
+    let jctv = sys.get_all_junctions();
+    if jctv.is_empty() {
+        println!(">>> There are no junctions in the track network <<<");
+        return 0;
+    }
+    let val = match enter_junction(&jctv) {
+        None => return 1,
+        Some(v) => v,
+    };
+    let rc = sys.toggle_switch(&jctv[val]);
+    if rc == 13 { println!("{} is clamped", &jctv[val]); }
+    if rc == 5 { println!("{} is stuck", &jctv[val]); }
+    if rc == 16 { println!("{} is already moving", &jctv[val]); }
+    if rc != 0 { return rc; }
     let mut jpos = common::JSwitch::JSwitchNone;
+    let mut moving = false;
     if let Some(node) = sys.get_node(&jctv[val]) {
-        node.toggle_switch_pos();
+        moving = node.is_in_motion();
+        jpos = if moving { node.moving_to } else { node.get_switch_pos() };
+    }
+    println!("{}: junction switch {} {}",
+        &jctv[val], if moving { "is moving to" } else { "is" },
+        if jpos == JSwitchLeft { "LEFT" } else { "RIGHT" });
+    0
+}
+fn cmd_toggle_reversing(sys: &mut System) -> i32 {
+    let mut resp = enter_name();
+    if resp.is_empty() { return 0; }
+    if sys.get_edge(&resp).is_none() {
+        let rnum = resp;
+        resp = name_from_number(&rnum);
+        if sys.get_edge(&resp).is_none() {
+            println!("No such segment \"{rnum}\"");
+            return 1;
+        }
+    }
+    let mut reversing = false;
+    if let Some(eref) = sys.get_edge(&resp) {
+        reversing = !eref.reversing;
+    }
+    let rc = sys.set_reversing(&resp, reversing);
+    if rc != 0 { return rc; }
+    println!("{}: trains {} reverse here", &resp, if reversing { "may" } else { "may not" });
+    0
+}
+fn cmd_toggle_closed(sys: &mut System) -> i32 {
+    let mut resp = enter_name();
+    if resp.is_empty() { return 0; }
+    if sys.get_edge(&resp).is_none() {
+        let rnum = resp;
+        resp = name_from_number(&rnum);
+        if sys.get_edge(&resp).is_none() {
+            println!("No such segment \"{rnum}\"");
+            return 1;
+        }
+    }
+    let mut closed = false;
+    if let Some(eref) = sys.get_edge(&resp) {
+        closed = !eref.closed;
+    }
+    let rc = sys.set_closed(&resp, closed);
+    if rc != 0 { return rc; }
+    println!("{}: {}", &resp, if closed { "closed" } else { "back in service" });
+    0
+}
+fn cmd_toggle_clamp(sys: &mut System) -> i32 {
+    let jctv = sys.get_all_junctions();
+    if jctv.is_empty() {
+        println!(">>> There are no junctions in the track network <<<");
+        return 0;
+    }
+    let val = match enter_junction(&jctv) {
+        None => return 1,
+        Some(v) => v,
+    };
+    let mut clamped = false;
+    let mut jpos = common::JSwitch::JSwitchNone;
+    if let Some(node) = sys.get_node(&jctv[val]) {
+        clamped = !node.clamped;
         jpos = node.get_switch_pos();
     }
-    println!("{}: junction switch is {}",
-        &jctv[val], if jpos == JSwitchLeft { "LEFT" } else { "RIGHT" });
+    let rc = sys.set_clamped(&jctv[val], clamped);
+    if rc == 16 { println!("{}: the switch is moving or stuck", &jctv[val]); }
+    if rc != 0 { return rc; }
+    if clamped {
+        println!("{}: junction is clamped {}",
+            &jctv[val], if jpos == JSwitchLeft { "LEFT" } else { "RIGHT" });
+    }
+    else {
+        println!("{}: junction is released", &jctv[val]);
+    }
+    0
+}
+fn cmd_toggle_trailable(sys: &mut System) -> i32 {
+    let jctv = sys.get_all_junctions();
+    if jctv.is_empty() {
+        println!(">>> There are no junctions in the track network <<<");
+        return 0;
+    }
+    let val = match enter_junction(&jctv) {
+        None => return 1,
+        Some(v) => v,
+    };
+    let mut trailable = false;
+    if let Some(node) = sys.get_node(&jctv[val]) {
+        trailable = !node.trailable;
+    }
+    let rc = sys.set_trailable(&jctv[val], trailable);
+    if rc != 0 { return rc; }
+    println!("{}: junction {} be run through",
+        &jctv[val], if trailable { "can" } else { "cannot" });
+    0
+}
+fn cmd_advise_signals(sys: &mut System) -> i32 {
+    let mut movements = vec![];
+    println!("Enter the expected train movements as \"start destination\",");
+    println!("one to a line (RETURN alone to finish, or to use the timetable)");
+    loop {
+        let mut resp = String::new();
+        print!("Movement: ");
+        io::stdout().flush().unwrap();
+        match io::stdin().read_line(&mut resp) {
+            Ok(_)   => resp = resp.trim().to_string(),
+            Err(_)  => resp.clear(),
+        }
+        if resp.is_empty() { break; }
+        let mut names = vec![];
+        for field in resp.split_whitespace() {
+            let mut name = String::from(field);
+            if sys.get_edge(&name).is_none() {
+                name = name_from_number(&name);
+            }
+            if sys.get_edge(&name).is_none() {
+                println!("No such segment \"{}\"", field);
+                return 1;
+            }
+            names.push(name);
+        }
+        if names.len() != 2 {
+            println!("Expected a start and a destination, got \"{resp}\"");
+            return 22; // EINVAL
+        }
+        if sys.find_route(&names[0], &names[1]).is_none() {
+            println!("WARNING: No route from \"{}\" to \"{}\"", &names[0], &names[1]);
+            continue;
+        }
+        movements.push(Movement { start: names[0].clone(), calls: vec![names[1].clone()] });
+    }
+    if movements.is_empty() {
+        movements = sys.timetable_movements();
+        if movements.is_empty() {
+            println!("No movements entered and no timetable loaded, quitting...");
+            return 0;
+        }
+        println!("Using the {} service(s) of the timetable", movements.len());
+    }
 
-    sys.update_all_signals();
-    return 0;
+    let mut resp = String::new();
+    print!("Headway in ticks (0 not to check block lengths) [{ADVISOR_HEADWAY}]: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut resp) {
+        Ok(_)   => resp = resp.trim().to_string(),
+        Err(_)  => resp.clear(),
+    }
+    let headway = if resp.is_empty() { ADVISOR_HEADWAY } else {
+        match resp.parse() {
+            Ok(n) => n,
+            Err(_) => {
+                println!("Invalid entry \"{resp}\", quitting...");
+                return 22; // EINVAL
+            }
+        }
+    };
+
+    let advice = sys.advise_signals(&movements, headway);
+    print!("{}", advisor::format_advice(&advice));
+    if advice.is_empty() { return 0; }
+    let mut resp = String::new();
+    print!("Apply the advice (y/n) [n]: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut resp) {
+        Ok(_)   => resp = resp.trim().to_lowercase(),
+        Err(_)  => resp.clear(),
+    }
+    if resp == "y" {
+        let changed = sys.apply_signal_advice(&advice);
+        println!("Changed {changed} signal(s)");
+    }
+    0
 }
 fn cmd_list_segments(sys: &System) -> i32 {
-    sys.show_edges();
-    return 0;
+    print!("{}", sys.format_edges());
+    print!("{}", sys.format_blocks());
+    0
 }
 fn cmd_show_connections(sys: &System) -> i32 {
-    sys.show_nodes();
-    return 0;
+    print!("{}", sys.format_nodes());
+    0
 }
 fn cmd_place_train(sys: &mut System) -> i32 {
     let mut tname = String::new();
@@ -234,7 +419,7 @@ fn cmd_place_train(sys: &mut System) -> i32 {
     }
     else {
         let topt = sys.get_train(&tname);
-        if let None = topt {
+        if topt.is_none() {
             println!("No such train: \"{}\"", &tname);
             return 1;
         }
@@ -244,10 +429,10 @@ fn cmd_place_train(sys: &mut System) -> i32 {
     let resp = enter_name();
     if resp.is_empty() { return 0; }
     let mut eopt = sys.get_edge(&resp);
-    if let None = eopt {
+    if eopt.is_none() {
         ename1 = name_from_number(&resp);
         eopt = sys.get_edge(&ename1);
-        if let None = eopt {
+        if eopt.is_none() {
             println!("No such segment \"{}\"", &resp);
             return 1;
         }
@@ -266,10 +451,10 @@ fn cmd_place_train(sys: &mut System) -> i32 {
     let resp = enter_name();
     if resp.is_empty() { return 0; }
     let mut eopt = sys.get_edge(&resp);
-    if let None = eopt {
+    if eopt.is_none() {
         ename2 = name_from_number(&resp);
         eopt = sys.get_edge(&ename2);
-        if let None = eopt {
+        if eopt.is_none() {
             println!("No such segment \"{}\"", &resp);
             return 1;
         }
@@ -278,66 +463,436 @@ fn cmd_place_train(sys: &mut System) -> i32 {
         ename2 = resp;
     }
 
-    let mut ename = "";
+    let mut resp = String::new();
+    print!("Enter waypoints as segment[+dwell], comma separated (RETURN for none): ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut resp) {
+        Ok(_)   => resp = resp.trim().to_string(),
+        Err(_)  => resp.clear(),
+    }
+    let mut waypoints = vec![];
+    for field in resp.split(',').map(|f| f.trim()).filter(|f| !f.is_empty()) {
+        match Waypoint::parse(field) {
+            Some(mut wp) => {
+                if sys.get_edge(&wp.segment).is_none() {
+                    wp.segment = name_from_number(&wp.segment);
+                }
+                if sys.get_edge(&wp.segment).is_none() {
+                    println!("No such segment \"{}\"", field);
+                    return 1;
+                }
+                waypoints.push(wp);
+            }
+            None => {
+                println!("Invalid waypoint \"{}\"", field);
+                return 1;
+            }
+        }
+    }
+
+    let mut rc = sys.place_train(&tname, &ename1, &ename2);
+    if rc == 0 && !waypoints.is_empty() {
+        rc = sys.set_train_waypoints(&tname, waypoints);
+    }
+    if rc != 0 {
+        println!("WARNING: No route from \"{}\" to \"{}\"", &ename1, &ename2);
+    }
     if let Some(tref) = sys.get_train(&tname) {
-        let edge = tref.get_position();
-        ename = edge.ee_edge.as_str();
+        print!("{}", tref.format(sys));
+        if !tref.route.is_empty() {
+            if let Some(cost) = sys.train_route_cost(&tname) {
+                print!("{}", cost.format(sys.get_cost_weights()));
+            }
+        }
     }
-    if let Some(gref) = sys.get_edge_mut(&String::from(ename)) {
-        gref.set_train("");
+    0
+}
+fn cmd_step_simulation(sys: &mut System) -> i32 {
+    let moved = sys.step();
+    if moved == 0 {
+        println!("No train is able to move, the simulation is complete.");
+    }
+    else {
+        println!("{moved} train(s) moved");
     }
-    if let Some(tref) = sys.get_train_mut(&tname) {
-        tref.place_on_track(&ename1, &ename2);
+    print!("{}", sys.format_trains());
+    if !sys.get_timetable().is_empty() {
+        print!("{}", sys.format_timetable_report());
+    }
+    if !sys.get_faults().is_empty() {
+        print!("{}", sys.format_fault_report());
+    }
+    if sys.is_halted() {
+        print!("{}", sys.format_safety_report());
+    }
+    0
+}
+fn cmd_run_simulation(sys: &mut System) -> i32 {
+    let steps = sys.run();
+    println!("Simulation complete after {steps} step(s)");
+    print!("{}", sys.format_trains());
+    if !sys.get_timetable().is_empty() {
+        print!("{}", sys.format_timetable_report());
     }
-    if let Some(eref) = sys.get_edge_mut(&ename1) {
-        eref.set_train(tname.as_str());
+    if !sys.get_faults().is_empty() {
+        print!("{}", sys.format_fault_report());
+    }
+    if sys.is_halted() {
+        print!("{}", sys.format_safety_report());
+    }
+    0
+}
+fn cmd_force_train(sys: &mut System) -> i32 {
+    let mut tname = String::new();
+    print!("Enter train name: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut tname) {
+        Ok(_)   => tname = tname.trim_end().to_string(),
+        Err(_)  => tname.clear(),
     }
-    sys.update_all_signals();
+    if tname.is_empty() { return 0; }
+    let rc = sys.force_train(&tname);
+    match rc {
+        2 => println!("No such train: \"{}\"", &tname),
+        16 => println!("The way ahead of {} is not clear", &tname),
+        22 => println!("{} has no move to make on authority", &tname),
+        _ => (),
+    }
+    if rc != 0 { return rc; }
     if let Some(tref) = sys.get_train(&tname) {
-        tref.show(sys);
+        print!("{}", tref.format(sys));
+    }
+    if sys.is_halted() {
+        print!("{}", sys.format_safety_report());
+    }
+    0
+}
+fn cmd_save_network(sys: &System) -> i32 {
+    let mut path = String::new();
+    print!("Enter file path: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut path) {
+        Ok(_)   => path = path.trim().to_string(),
+        Err(_)  => path.clear(),
+    }
+    if path.is_empty() {
+        println!("No response, quitting...");
+        return 0;
+    }
+    let mut file;
+    match File::create(&path) {
+        Ok(f) => file = f,
+        Err(_) => {
+            println!("Unable to create {path}, quitting...");
+            return 13; // EACCES
+        }
+    }
+    let rc = sys.serialize(&mut file);
+    if rc == 0 {
+        println!("Saved {} track segments to {path}", sys.get_all_edges().len());
+    }
+    rc
+}
+fn cmd_export_svg(sys: &System) -> i32 {
+    let mut path = String::new();
+    print!("Enter SVG file path: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut path) {
+        Ok(_)   => path = path.trim().to_string(),
+        Err(_)  => path.clear(),
+    }
+    if path.is_empty() {
+        println!("No response, quitting...");
+        return 0;
+    }
+    match fs::write(&path, sys.to_svg()) {
+        Ok(_) => println!("Wrote a snapshot of the network to {path}"),
+        Err(_) => {
+            println!("Unable to write {path}, quitting...");
+            return 13; // EACCES
+        }
     }
-    return 0;
+    0
 }
-fn cmd_step_simulation() -> i32 {
-    println!("Here is where we step the train simulation.");
-    return 0;
+fn cmd_record_replay(sys: &mut System) -> i32 {
+    let mut path = String::new();
+    print!("Enter SVG file path, or a directory for one file per tick: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut path) {
+        Ok(_)   => path = path.trim().to_string(),
+        Err(_)  => path.clear(),
+    }
+    if path.is_empty() {
+        println!("No response, quitting...");
+        return 0;
+    }
+    let replay = sys.run_recorded();
+    println!("Simulation complete after {} step(s)", replay.frames.len() - 1);
+
+    let mut files = vec![];
+    if Path::new(&path).is_dir() {
+        for (tick, frame) in sys.replay_frames(&replay).into_iter().enumerate() {
+            files.push((Path::new(&path).join(format!("tick{tick:04}.svg")), frame));
+        }
+    }
+    else {
+        files.push((PathBuf::from(&path), sys.replay_to_svg(&replay, REPLAY_TICK_SECS)));
+    }
+    for (file, svg) in &files {
+        if fs::write(file, svg).is_err() {
+            println!("Unable to write {}, quitting...", file.display());
+            return 13; // EACCES
+        }
+    }
+    println!("Wrote a replay of {} tick(s) to {path}", replay.frames.len());
+    0
 }
-fn cmd_run_simulation() -> i32 {
-    println!("Here is where we run the simulation until complete.");
-    return 0;
+fn cmd_save_event_log(sys: &System) -> i32 {
+    let mut path = String::new();
+    print!("Enter file path (.csv, or .jsonl for JSON lines): ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut path) {
+        Ok(_)   => path = path.trim().to_string(),
+        Err(_)  => path.clear(),
+    }
+    if path.is_empty() {
+        println!("No response, quitting...");
+        return 0;
+    }
+    let mut file;
+    match File::create(&path) {
+        Ok(f) => file = f,
+        Err(_) => {
+            println!("Unable to create {path}, quitting...");
+            return 13; // EACCES
+        }
+    }
+    let json = path.ends_with(".jsonl") || path.ends_with(".json");
+    let rc = if json { sys.write_events_json(&mut file) } else { sys.write_events_csv(&mut file) };
+    if rc == 0 {
+        println!("Saved {} event(s) to {path}", sys.get_events().len());
+    }
+    rc
 }
-fn cmd_save_network() -> i32 {
-    println!("Here is where we save the current network.");
-    return 0;
+fn cmd_show_statistics(sys: &System) -> i32 {
+    let report = sys.compute_statistics().format();
+    print!("{report}");
+    let mut path = String::new();
+    print!("Enter file path to save the report (RETURN to skip): ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut path) {
+        Ok(_)   => path = path.trim().to_string(),
+        Err(_)  => path.clear(),
+    }
+    if path.is_empty() {
+        return 0;
+    }
+    match fs::write(&path, report) {
+        Ok(_) => println!("Saved the report to {path}"),
+        Err(_) => {
+            println!("Unable to write {path}, quitting...");
+            return 13; // EACCES
+        }
+    }
+    0
 }
-fn cmd_load_network() -> i32 {
-    if edge_count() != 0 {
+fn cmd_load_timetable(sys: &mut System) -> i32 {
+    let mut path = String::new();
+    print!("Enter timetable file path: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut path) {
+        Ok(_)   => path = path.trim().to_string(),
+        Err(_)  => path.clear(),
+    }
+    if path.is_empty() {
+        println!("No response, quitting...");
+        return 0;
+    }
+    let file = match File::open(&path) {
+        Ok(f) => f,
+        Err(_) => {
+            println!("{path} not found, quitting...");
+            return 2; // ENOENT
+        }
+    };
+    let rc = sys.load_timetable(&mut BufReader::new(file));
+    if rc == 0 {
+        println!("Loaded {} service(s) from {path}", sys.get_timetable().len());
+    }
+    rc
+}
+fn cmd_set_routing_options(sys: &mut System) -> i32 {
+    let mut weights = *sys.get_cost_weights();
+    let mut wait = sys.get_reroute_wait();
+    println!("Press RETURN to keep a setting as it is");
+    for (name, weight) in [("Weight per segment length", &mut weights.length),
+                           ("Weight per junction, straight leg", &mut weights.straight),
+                           ("Weight per junction, diverging leg", &mut weights.diverging),
+                           ("Weight per reversal", &mut weights.reversal),
+                           ("Weight per occupied segment", &mut weights.occupied),
+                           ("Ticks held before rerouting (0 for never)", &mut wait)] {
+        let mut resp = String::new();
+        print!("{name} [{weight}]: ");
+        io::stdout().flush().unwrap();
+        match io::stdin().read_line(&mut resp) {
+            Ok(_)   => resp = resp.trim().to_string(),
+            Err(_)  => resp.clear(),
+        }
+        if resp.is_empty() { continue; }
+        match resp.parse() {
+            Ok(n) => *weight = n,
+            Err(_) => {
+                println!("Invalid entry \"{resp}\", quitting...");
+                return 22; // EINVAL
+            }
+        }
+    }
+    sys.set_cost_weights(weights);
+    sys.set_reroute_wait(wait);
+    println!("The new weights apply to routes planned from now on, and the wait to trains held now");
+    0
+}
+fn cmd_set_switch_options(sys: &mut System) -> i32 {
+    let mut resp = String::new();
+    let throw_ticks = sys.get_throw_ticks();
+    println!("Press RETURN to keep a setting as it is");
+    print!("Ticks to throw a switch (0 for at once) [{throw_ticks}]: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut resp) {
+        Ok(_)   => resp = resp.trim().to_string(),
+        Err(_)  => resp.clear(),
+    }
+    let throw_ticks = if resp.is_empty() { throw_ticks } else {
+        match resp.parse() {
+            Ok(n) => n,
+            Err(_) => {
+                println!("Invalid entry \"{resp}\", quitting...");
+                return 22; // EINVAL
+            }
+        }
+    };
+    let mut resp = String::new();
+    let auto = sys.get_auto_switching();
+    print!("Set switches along train routes automatically (y/n) [{}]: ", if auto { "y" } else { "n" });
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut resp) {
+        Ok(_)   => resp = resp.trim().to_lowercase(),
+        Err(_)  => resp.clear(),
+    }
+    let auto = match resp.as_str() {
+        "" => auto,
+        "y" => true,
+        "n" => false,
+        _ => {
+            println!("Invalid entry \"{resp}\", quitting...");
+            return 22; // EINVAL
+        }
+    };
+    sys.set_throw_ticks(throw_ticks);
+    sys.set_auto_switching(auto);
+    println!("Switches thrown from now on take {throw_ticks} tick(s), and are {} along train routes",
+             if auto { "set automatically" } else { "only thrown by hand" });
+    0
+}
+fn cmd_toggle_fault(sys: &mut System) -> i32 {
+    let mut resp = String::new();
+    print!("Fault: [s]witch stuck, [l]amp out, [t]rack detector occupied, detector failed [c]lear (RETURN to list): ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut resp) {
+        Ok(_)   => resp = resp.trim().to_lowercase(),
+        Err(_)  => resp.clear(),
+    }
+    let fault;
+    match resp.as_str() {
+        "" => {
+            print!("{}", sys.format_fault_report());
+            return 0;
+        }
+        "s" => {
+            let jctv = sys.get_all_junctions();
+            if jctv.is_empty() {
+                println!(">>> There are no junctions in the track network <<<");
+                return 0;
+            }
+            match enter_junction(&jctv) {
+                None => return 1,
+                Some(v) => fault = Fault::SwitchStuck { node: jctv[v].clone(), position: common::JSwitch::JSwitchNone },
+            }
+        }
+        "l" | "t" | "c" => {
+            let mut name = enter_name();
+            if name.is_empty() { return 0; }
+            if sys.get_edge(&name).is_none() {
+                let rnum = name;
+                name = name_from_number(&rnum);
+                if sys.get_edge(&name).is_none() {
+                    println!("No such segment \"{rnum}\"");
+                    return 1;
+                }
+            }
+            if resp == "l" { fault = Fault::LampOut { segment: name, end: enter_a_or_b() }; }
+            else if resp == "t" { fault = Fault::FalseOccupied { segment: name }; }
+            else { fault = Fault::FalseClear { segment: name }; }
+        }
+        _ => {
+            println!("Invalid entry: \"{resp}\"");
+            return 22; // EINVAL
+        }
+    }
+    // A fault already in effect is cleared.
+    let rc;
+    if sys.get_faults().contains(&fault) {
+        rc = sys.clear_fault(&fault);
+        if rc == 0 { println!("Cleared: {}", fault.describe()); }
+        else if rc == 16 { println!("A train stands on the junction; it cannot be moved"); }
+    }
+    else {
+        rc = sys.inject_fault(fault.clone());
+        if rc == 0 { println!("Injected: {}", fault.describe()); }
+        else if rc == 22 { println!("There is no {}", fault.name()); }
+        else if rc == 16 { println!("The junction is clamped; release it first"); }
+    }
+    if rc == 0 { print!("{}", sys.format_fault_report()); }
+    if rc == 0 && matches!(fault, Fault::FalseOccupied { .. } | Fault::FalseClear { .. }) {
+        print!("{}", sys.format_detection());
+    }
+    rc
+}
+fn cmd_load_network(sys: &mut System) -> i32 {
+    if sys.edge_count() != 0 {
         println!("WARNING: This will delete the existing network");
         println!("         Press RETURN key at the prompt to quit");
     }
-/*  std::string path;
-    std::cout << "Enter file path: ";
-    std::getline(std::cin, path);
-    if (path.empty()) {
-        std::cout << "No response, quitting..." << std::endl;
+    let mut path = String::new();
+    print!("Enter file path: ");
+    io::stdout().flush().unwrap();
+    match io::stdin().read_line(&mut path) {
+        Ok(_)   => path = path.trim().to_string(),
+        Err(_)  => path.clear(),
+    }
+    if path.is_empty() {
+        println!("No response, quitting...");
         return 0;
     }
-    std::ifstream ifstr(path);
-    if (!ifstr.good()) {
-        std::cout << path << " not found, quitting..." << std::endl;
-        return ENOENT;
+    load_network_file(sys, &path)
+}
+fn load_network_file(sys: &mut System, path: &str) -> i32 {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => {
+            println!("{path} not found, quitting...");
+            return 2; // ENOENT
+        }
+    };
+    let rc = sys.deserialize(&mut BufReader::new(file));
+    if rc == 0 {
+        println!("Loaded {} track segments from {path}", sys.edge_count());
     }
-
-    int rc = sys().deserialize(ifstr);
-    ifstr.close();
-    return rc;
-
-    println!("Here is where we load a saved track network."); */
-    return 0;
+    rc
 }
 fn cmd_signal_all_junctions() -> i32 {
     println!("Here is where we add a signal to all junctions.");
-    return 0;
+    0
 }
 
 fn run_command_build(sys: &mut System) -> i32 {
@@ -351,6 +906,11 @@ fn run_command_build(sys: &mut System) -> i32 {
     println!("6. Save track network");
     println!("7. Load track network");
     println!("8. Add Signals To All Junctions");
+    println!("9. Toggle a reversing segment");
+    println!("10. Close or reopen a track segment");
+    println!("11. Clamp or release a junction");
+    println!("12. Make a junction trailable or not");
+    println!("13. Advise on signal placement");
     println!("R/return");
 
     let mut resp = String::new();
@@ -358,6 +918,7 @@ fn run_command_build(sys: &mut System) -> i32 {
         print!("=> ");
         io::stdout().flush().unwrap();
         match io::stdin().read_line(&mut resp) {
+            Ok(0)   => return 1, // End of input.
             Ok(_)   => resp = resp.trim_end().to_string(),
             Err(_)  => resp.clear(),
         }
@@ -366,8 +927,7 @@ fn run_command_build(sys: &mut System) -> i32 {
         return 1;
     }
 
-    let cmd;
-    match resp.trim().parse() { Ok(n) => cmd = n, Err(_) => cmd = 0 }
+    let cmd: i32 = resp.trim().parse().unwrap_or(0);
 
     let rc;
 
@@ -399,12 +959,12 @@ fn run_command_build(sys: &mut System) -> i32 {
         }
         6 => {
             println!("---------------- Save Track Network ----------------");
-            rc = cmd_save_network();
+            rc = cmd_save_network(sys);
             println!("----------------------------------------------------");
         }
         7 => {
             println!("---------------- Load Track Network ----------------");
-            rc = cmd_load_network();
+            rc = cmd_load_network(sys);
             println!("----------------------------------------------------");
         }
         8 => {
@@ -412,6 +972,31 @@ fn run_command_build(sys: &mut System) -> i32 {
             rc = cmd_signal_all_junctions();
             println!("----------------------------------------------------");
         }
+        9 => {
+            println!("------------- Toggle Reversing Segment -------------");
+            rc = cmd_toggle_reversing(sys);
+            println!("----------------------------------------------------");
+        }
+        10 => {
+            println!("-------------- Close Or Reopen Segment -------------");
+            rc = cmd_toggle_closed(sys);
+            println!("----------------------------------------------------");
+        }
+        11 => {
+            println!("------------- Clamp Or Release Junction ------------");
+            rc = cmd_toggle_clamp(sys);
+            println!("----------------------------------------------------");
+        }
+        12 => {
+            println!("------------- Toggle Trailable Junction ------------");
+            rc = cmd_toggle_trailable(sys);
+            println!("----------------------------------------------------");
+        }
+        13 => {
+            println!("------------- Advise On Signal Placement -----------");
+            rc = cmd_advise_signals(sys);
+            println!("----------------------------------------------------");
+        }
         _ => {
             println!("Invalid entry: \"{resp}\"");
             rc = 21;
@@ -419,7 +1004,7 @@ fn run_command_build(sys: &mut System) -> i32 {
         }
     }
     if rc != 0 { println!("(Error code {rc} was returned)"); }
-    return 0;
+    0
 }
 
 fn run_command(sys: &mut System) -> i32 {
@@ -431,6 +1016,15 @@ fn run_command(sys: &mut System) -> i32 {
     println!("4. Place train on a track segment");
     println!("5. [S]tep the train simulation");
     println!("6. [R]un the train simulation");
+    println!("7. Export a snapshot as SVG");
+    println!("8. Run and export an SVG replay");
+    println!("9. Save the event log");
+    println!("10. Show simulation statistics");
+    println!("11. Load a timetable");
+    println!("12. Set routing options");
+    println!("13. Inject or clear a fault");
+    println!("14. Authorise a train past its signal");
+    println!("15. Set switch options");
     println!("Q/quit/exit");
 
     let mut resp = String::new();
@@ -439,6 +1033,7 @@ fn run_command(sys: &mut System) -> i32 {
         print!("=> ");
         io::stdout().flush().unwrap();
         match io::stdin().read_line(&mut resp) {
+            Ok(0)   => return 1, // End of input.
             Ok(_)   => resp = resp.trim_end().to_string(),
             Err(_)  => resp.clear(),
         }
@@ -477,12 +1072,57 @@ fn run_command(sys: &mut System) -> i32 {
         }
         5 => {
             println!("----------------- Step Simulation ------------------");
-            rc = cmd_step_simulation();
+            rc = cmd_step_simulation(sys);
             println!("----------------------------------------------------");
         }
         6 => {
             println!("------------------ Run Simulation ------------------");
-            rc = cmd_run_simulation();
+            rc = cmd_run_simulation(sys);
+            println!("----------------------------------------------------");
+        }
+        7 => {
+            println!("------------------- Export SVG ---------------------");
+            rc = cmd_export_svg(sys);
+            println!("----------------------------------------------------");
+        }
+        8 => {
+            println!("------------------ Record Replay -------------------");
+            rc = cmd_record_replay(sys);
+            println!("----------------------------------------------------");
+        }
+        9 => {
+            println!("------------------ Save Event Log ------------------");
+            rc = cmd_save_event_log(sys);
+            println!("----------------------------------------------------");
+        }
+        10 => {
+            println!("--------------- Simulation Statistics --------------");
+            rc = cmd_show_statistics(sys);
+            println!("----------------------------------------------------");
+        }
+        11 => {
+            println!("----------------- Load Timetable -------------------");
+            rc = cmd_load_timetable(sys);
+            println!("----------------------------------------------------");
+        }
+        12 => {
+            println!("----------------- Routing Options ------------------");
+            rc = cmd_set_routing_options(sys);
+            println!("----------------------------------------------------");
+        }
+        13 => {
+            println!("------------------ Fault Injection -----------------");
+            rc = cmd_toggle_fault(sys);
+            println!("----------------------------------------------------");
+        }
+        14 => {
+            println!("---------------- Authorise A Train -----------------");
+            rc = cmd_force_train(sys);
+            println!("----------------------------------------------------");
+        }
+        15 => {
+            println!("------------------ Switch Options ------------------");
+            rc = cmd_set_switch_options(sys);
             println!("----------------------------------------------------");
         }
         _ => {
@@ -492,19 +1132,39 @@ fn run_command(sys: &mut System) -> i32 {
         }
     }
     if rc != 0 { println!("(Error code {rc} was returned)"); }
-    return 0;
+    0
 }
 
 // ------------------------------------------------------------------
 // main -- Entry point
 // ------------------------------------------------------------------
 
+// Usage: signaling [--tui] [network-file]
+//
 fn main() {
     println!("Case Study Implementation -- Railroad Signaling System");
     println!("Version {SG_VERSION_MAJOR}.{SG_VERSION_MINOR}");
 
     let mut sys = system::create_system();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(path) = args.iter().find(|a| !a.starts_with("--")) {
+        if load_network_file(&mut sys, path) != 0 {
+            std::process::exit(1);
+        }
+    }
+
+    if args.iter().any(|a| a == "--tui") {
+        #[cfg(feature = "tui")]
+        if let Err(e) = signaling::tui::run(&mut sys) {
+            println!("ERROR: Terminal interface failed: {e}");
+            std::process::exit(1);
+        }
+        #[cfg(not(feature = "tui"))]
+        println!("ERROR: Built without the terminal interface (feature \"tui\")");
+        return;
+    }
+
     while run_command(&mut sys) == 0 {}
 }
 
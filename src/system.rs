@@ -18,11 +18,32 @@ pub mod node;
 use node::Node;
 
 pub mod train;
-use train::Train;
+use train::{Train, Waypoint};
+
+pub mod route;
+pub mod simulation;
+pub mod layout;
+pub mod svg;
+pub mod replay;
+pub mod events;
+pub mod stats;
+pub mod timetable;
+use timetable::Service;
+pub mod faults;
+use faults::Fault;
+pub mod safety;
+use safety::SafetyLog;
+pub mod detection;
+pub mod blocks;
+pub mod advisor;
+use route::CostWeights;
+use events::{Event, LogEntry};
 
 type NodeVec = Vec<String>;
 
 use std::collections::HashMap;
+use std::io::BufRead;
+use std::io::Write;
 use itertools::Itertools;
 
 pub struct System
@@ -30,6 +51,15 @@ pub struct System
     edge_map:   HashMap<String, Edge>,
     node_map:   HashMap<String, Node>,
     train_map:  HashMap<String, Train>,
+    tick:       u32,
+    events:     Vec<LogEntry>,
+    timetable:  Vec<Service>,
+    cost_weights: CostWeights,
+    reroute_wait: u32,
+    faults:     Vec<Fault>,
+    safety:     SafetyLog,
+    throw_ticks: u32,
+    auto_switching: bool,
 }
 
 impl System {
@@ -44,25 +74,11 @@ impl System {
         else {
             edge_name = String::from(name);
             // Verify the given edge name is unique.
-            match self.edge_map.get(&edge_name) {
-                Some(_) => return None,
-                _ => (),
-            }
+            if self.edge_map.contains_key(&edge_name) { return None; }
         }
         assert!(! edge_name.is_empty(), "The edge name is empty");
 
-        let edge = Edge {
-            name: edge_name.clone(),
-            ends: [ NodeSlot { ns_node: String::new(), ns_slot: SLOT_1, },
-                    NodeSlot { ns_node: String::new(), ns_slot: SLOT_1, } ],
-            signals: [ RRsignal { is_red: false, edge:
-                           EdgeEnd { ee_edge: String::new(),
-                                     ee_end: NUM_ENDS } },
-                       RRsignal { is_red: false, edge:
-                           EdgeEnd { ee_edge: String::new(),
-                                     ee_end: NUM_ENDS } } ],
-            train: String::new(),
-        };
+        let edge = new_edge(&edge_name);
         match self.edge_map.insert(edge_name.clone(), edge) {
             None => (),
             Some(_) => panic!("Insert for {} failed", &edge_name),
@@ -101,12 +117,7 @@ impl System {
         self.edge_map.get_mut(name)
     }
     pub fn has_edge(&self, name: &String) -> bool {
-        let rval;
-        match self.edge_map.get(name) {
-            None    => rval = false,
-            Some(_) => rval = true,
-        }
-        rval
+        self.edge_map.contains_key(name)
     }
 
     // ==============================================================
@@ -120,10 +131,7 @@ impl System {
         else {
             node_name = String::from(name);
             // Verify the given node name is unique.
-            match self.node_map.get(&node_name) {
-                Some(_) => return None,
-                _ => (),
-            }
+            if self.node_map.contains_key(&node_name) { return None; }
         }
         assert!(! node_name.is_empty(), "The node name is empty");
         let node = Node {
@@ -132,6 +140,12 @@ impl System {
                      EdgeEnd { ee_edge: String::new(), ee_end: END_A },
                      EdgeEnd { ee_edge: String::new(), ee_end: END_A }, ],
             switch_state: JSwitch::JSwitchNone,
+            position: None,
+            fixed_position: false,
+            clamped: false,
+            trailable: false,
+            moving_to: JSwitch::JSwitchNone,
+            throw_remaining: 0,
         };
         self.node_map.insert(node_name.clone(), node);
         self.node_map.get_mut(&node_name)
@@ -140,29 +154,91 @@ impl System {
     pub fn get_node(&mut self, name: &String) -> Option<&mut Node> {
         self.node_map.get_mut(name)
     }
+    pub fn get_node_ref(&self, name: &String) -> Option<&Node> {
+        self.node_map.get(name)
+    }
 
     // ==============================================================
     // create_train
     // ==============================================================
     pub fn create_train(&mut self, name: &str) -> Option<&Train> {
         // Verify the name is not already used.
-        if let Some(_) = self.train_map.get(name) { return None; }
-        let tname;
-        if name.is_empty() {
-            tname = self.get_unique_train_name();
+        if self.train_map.contains_key(name) { return None; }
+        let tname = if name.is_empty() {
+            self.get_unique_train_name()
         }
         else {
-            tname = String::from(name);
-        }
+            String::from(name)
+        };
         let train = Train {
             name: tname.clone(),
             edge: EdgeEnd { ee_edge: String::new(), ee_end: NUM_ENDS },
             destination: String::new(),
+            route: vec![],
+            waypoints: vec![],
+            next_waypoint: 0,
+            dwell_remaining: 0,
+            wait_ticks: 0,
+            last_node: String::new(),
+            derailed_at: None,
         };
         self.train_map.insert(train.name.clone(), train);
         self.train_map.get(&tname)
     }
 
+    // ==============================================================
+    // place_train
+    // ==============================================================
+    // Moves a train onto the start segment, bound for the destination
+    // segment, plans its route and updates the signals. The train is
+    // placed even if no route can be found, in which case 1 is
+    // returned and it will not move.
+    pub fn place_train(&mut self, name: &str, start: &String, dest: &String) -> i32 {
+        let from = match self.train_map.get(name) {
+            None => return 2, // ENOENT
+            Some(t) => t.edge.ee_edge.clone(),
+        };
+        match self.edge_map.get(start) {
+            None => return 2, // ENOENT
+            Some(e) => if !(e.train.is_empty() || e.train == name) { return 16; } // EBUSY
+        }
+        if !self.edge_map.contains_key(dest) { return 2; } // ENOENT
+
+        if let Some(e) = self.edge_map.get_mut(&from) {
+            e.set_train("");
+        }
+        if let Some(t) = self.train_map.get_mut(name) {
+            t.place_on_track(start, dest);
+        }
+        if let Some(e) = self.edge_map.get_mut(start) {
+            e.set_train(name);
+        }
+        self.log_event(Event::Placed { train: name.to_string(), segment: start.clone() });
+        let rc = self.get_optimal_route(name);
+        self.update_all_signals();
+        rc
+    }
+
+    // ==============================================================
+    // set_train_waypoints
+    // ==============================================================
+    // Gives the named train segments to pass through, in order, on
+    // its way to its destination, each with the ticks to stop there
+    // for, and plans its route through them. Returns the result of
+    // planning the route.
+    pub fn set_train_waypoints(&mut self, name: &str, waypoints: Vec<Waypoint>) -> i32 {
+        if waypoints.iter().any(|w| !self.edge_map.contains_key(&w.segment)) {
+            return 2; // ENOENT
+        }
+        match self.train_map.get_mut(name) {
+            None => return 2, // ENOENT
+            Some(t) => t.set_waypoints(waypoints),
+        }
+        let rc = self.get_optimal_route(name);
+        self.update_all_signals();
+        rc
+    }
+
     pub fn get_train(&self, name: &str) -> Option<&Train> {
         self.train_map.get(name)
     }
@@ -175,17 +251,14 @@ impl System {
     pub fn connect_segments(&mut self, s1: &EdgeEnd, s2: &EdgeEnd) -> i32
     {
         // If either track is invalid, there is nothing more to do.
-        let edge1;
-        let edge2;
-
-        match self.edge_map.get(&s1.ee_edge) {
+        let edge1 = match self.edge_map.get(&s1.ee_edge) {
             None => return 1, // EINVAL
-            Some(e) => edge1 = e,
-        }
-        match self.edge_map.get(&s2.ee_edge) {
+            Some(e) => e,
+        };
+        let edge2 = match self.edge_map.get(&s2.ee_edge) {
             None => return 1, // EINVAL
-            Some(e) => edge2 = e,
-        }
+            Some(e) => e,
+        };
 
         let cnct_node = edge1.get_node(s1.ee_end);
         let rmov_node = edge2.get_node(s2.ee_end);
@@ -205,12 +278,15 @@ impl System {
                 }
             }
         }
+        if cnct_node.ns_node == rmov_node.ns_node {
+            println!("ERROR: Cannot connect a track end to itself");
+            return 1; // EINVAL
+        }
 
-        let node1;
-        match self.node_map.get_mut(&cnct_node.ns_node) {
+        let node1 = match self.node_map.get_mut(&cnct_node.ns_node) {
             None => return 1, // throw
-            Some(n) => node1 = n,
-        }
+            Some(n) => n,
+        };
 
         // Connect to the other track as implied by this track's connection.
         match node1.get_node_type() {
@@ -285,23 +361,52 @@ impl System {
                 return 1; // throw
             }
         }
-        return 0;
+
+        // The other track's end now belongs to this node, so its old
+        // terminator node is no longer connected to anything.
+        self.node_map.remove(&rmov_node.ns_node);
+        0
+    }
+
+    pub fn get_all_edges(&self) -> Vec<String> {
+        self.edge_map.keys().sorted().cloned().collect()
+    }
+
+    pub fn get_all_nodes(&self) -> NodeVec {
+        self.node_map.keys().sorted().cloned().collect()
     }
 
     pub fn get_all_junctions(&self) -> NodeVec {
         let mut rval = vec![];
-        for (name, node) in &self.node_map {
-            if node.get_node_type() == NodeType::Junction {
+        for name in self.node_map.keys().sorted() {
+            if self.node_map[name].get_node_type() == NodeType::Junction {
                 rval.push(name.clone());
             }
         }
         rval
     }
 
+    pub fn edge_count(&self) -> usize {
+        self.edge_map.len()
+    }
+
+    // Removes all track segments, nodes, trains and the timetable,
+    // and starts the simulation clock and event log over.
+    pub fn reset_track_network(&mut self) {
+        self.edge_map.clear();
+        self.node_map.clear();
+        self.train_map.clear();
+        self.tick = 0;
+        self.events.clear();
+        self.timetable.clear();
+        self.faults.clear();
+        self.safety.clear();
+    }
+
     fn get_unique_edge_name(&self) -> String {
         let mut ix = 1;
         let mut name = String::from("tseg001");
-        while self.edge_map.get(&name).is_some() {
+        while self.edge_map.contains_key(&name) {
             ix += 1;
             name = format!("tseg{:03}", ix);
         }
@@ -311,7 +416,7 @@ impl System {
     fn get_unique_node_name(&self) -> String {
         let mut ix = 1;
         let mut name = String::from("node001");
-        while self.node_map.get(&name).is_some() {
+        while self.node_map.contains_key(&name) {
             ix += 1;
             name = format!("node{:03}", ix);
         }
@@ -321,28 +426,466 @@ impl System {
     fn get_unique_train_name(&self) -> String {
         let mut ix = 1;
         let mut name = String::from("train1");
-        while self.train_map.get(&name).is_some() {
+        while self.train_map.contains_key(&name) {
             ix += 1;
             name = format!("train{ix}");
         }
         name
     }
 
-    pub fn show_edges(&self) {
+    pub fn format_edges(&self) -> String {
+        let mut msg = String::new();
         for name in self.edge_map.keys().sorted() {
-            self.edge_map[name].show(self, NUM_ENDS);
+            msg += &self.edge_map[name].format(self, NUM_ENDS);
         }
-        println!("TOTAL: {} track segments", self.edge_map.len());
+        msg += &format!("TOTAL: {} track segments\n", self.edge_map.len());
+        msg
     }
 
-    pub fn show_nodes(&self) {
+    pub fn format_nodes(&self) -> String {
+        let mut msg = String::new();
         for name in self.node_map.keys().sorted() {
-            self.node_map[name].show(self);
+            msg += &self.node_map[name].format(self);
         }
+        msg
     }
 
+    // ==============================================================
+    // serialize
+    // ==============================================================
+    // Writes the track network, one line per track segment:
+    //
+    //   track: <name>,<length>,<node A>,<slot A>,<node B>,<slot B>,sigA:<Y|N>,sigB:<Y|N>
+    //
+    // Slots are zero based, as in the sample files under data/. Then
+    // come "reverse: <name>" for each reversing segment, "closed:
+    // <name>" for each closed segment, "clamp: <name>,<left|right>"
+    // for each clamped junction, "trailable: <name>" for each junction
+    // that can be run through, and "node: <name>,<x>,<y>" for each
+    // node with a fixed position.
+    pub fn serialize(&self, out: &mut dyn Write) -> i32 {
+        for name in self.edge_map.keys().sorted() {
+            let edge = &self.edge_map[name];
+            let line = format!("track: {},{},{},{},{},{},sigA:{},sigB:{}",
+                edge.name, edge.length,
+                edge.ends[END_A].ns_node, edge.ends[END_A].ns_slot,
+                edge.ends[END_B].ns_node, edge.ends[END_B].ns_slot,
+                if edge.has_signal(END_A) { "Y" } else { "N" },
+                if edge.has_signal(END_B) { "Y" } else { "N" });
+            if writeln!(out, "{line}").is_err() {
+                return 5; // EIO
+            }
+        }
+        for name in self.edge_map.keys().sorted() {
+            if !self.edge_map[name].reversing { continue; }
+            if writeln!(out, "reverse: {name}").is_err() {
+                return 5; // EIO
+            }
+        }
+        for name in self.edge_map.keys().sorted() {
+            if !self.edge_map[name].closed { continue; }
+            if writeln!(out, "closed: {name}").is_err() {
+                return 5; // EIO
+            }
+        }
+        for name in self.node_map.keys().sorted() {
+            let node = &self.node_map[name];
+            if !node.clamped { continue; }
+            let position = if node.get_switch_pos() == JSwitch::JSwitchRight { "right" }
+                           else { "left" };
+            if writeln!(out, "clamp: {name},{position}").is_err() {
+                return 5; // EIO
+            }
+        }
+        for name in self.node_map.keys().sorted() {
+            if !self.node_map[name].trailable { continue; }
+            if writeln!(out, "trailable: {name}").is_err() {
+                return 5; // EIO
+            }
+        }
+        for name in self.node_map.keys().sorted() {
+            let node = &self.node_map[name];
+            let pos = match node.position {
+                Some(p) if node.fixed_position => p,
+                _ => continue,
+            };
+            if writeln!(out, "node: {},{},{}", name, pos.x, pos.y).is_err() {
+                return 5; // EIO
+            }
+        }
+        0
+    }
+
+    // ==============================================================
+    // deserialize
+    // ==============================================================
+    // Replaces the current network with one read in the format written
+    // by serialize. Blank lines and lines starting with '#' are ignored.
+    // A "node:" line pins a node to the given schematic coordinates,
+    // which the automatic layout then leaves alone, and a "reverse:"
+    // line names a segment where trains may reverse. A "closed:" line
+    // takes a segment out of service. Junction switches start out set
+    // to the left fork, except that a "clamp:" line locks a junction
+    // in the given position. A "trailable:" line names a junction that
+    // trains may run through.
+    pub fn deserialize(&mut self, input: &mut dyn BufRead) -> i32 {
+        self.reset_track_network();
+        let mut clamps = vec![];
+        let mut trailables = vec![];
+        let mut line_num = 0;
+        for result in input.lines() {
+            line_num += 1;
+            let text = match result {
+                Ok(l) => l,
+                Err(_) => return 5, // EIO
+            };
+            let line = text.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+
+            let rc;
+            if let Some(rest) = line.strip_prefix("track:") {
+                rc = self.deserialize_track(rest);
+            }
+            else if let Some(rest) = line.strip_prefix("node:") {
+                rc = self.deserialize_node(rest);
+            }
+            else if let Some(rest) = line.strip_prefix("reverse:") {
+                rc = self.set_reversing(&String::from(rest.trim()), true);
+            }
+            else if let Some(rest) = line.strip_prefix("closed:") {
+                rc = self.set_closed(&String::from(rest.trim()), true);
+            }
+            else if let Some(rest) = line.strip_prefix("clamp:") {
+                match parse_clamp(rest) {
+                    Some(clamp) => { clamps.push(clamp); rc = 0; }
+                    None => rc = 22, // EINVAL
+                }
+            }
+            else if let Some(rest) = line.strip_prefix("trailable:") {
+                trailables.push(String::from(rest.trim()));
+                rc = 0;
+            }
+            else {
+                println!("ERROR: line {line_num}: unrecognized entry \"{line}\"");
+                rc = 22; // EINVAL
+            }
+            if rc != 0 {
+                println!("ERROR: line {line_num}: failed to load \"{line}\"");
+                self.reset_track_network();
+                return rc;
+            }
+        }
+
+        // Every node must have its slots filled in order.
+        for name in self.node_map.keys().sorted() {
+            let node = &self.node_map[name];
+            if node.get_node_type() == NodeType::Empty {
+                println!("ERROR: {name} is not connected to any track segment");
+                self.reset_track_network();
+                return 22; // EINVAL
+            }
+            let mut gap = false;
+            for slot in [SLOT_1, SLOT_2, SLOT_3] {
+                if node.slots[slot].ee_edge.is_empty() { gap = true; }
+                else if gap {
+                    println!("ERROR: {name} has slot {slot} connected but not the one before");
+                    self.reset_track_network();
+                    return 22; // EINVAL
+                }
+            }
+        }
+        for node in self.node_map.values_mut() {
+            if node.get_node_type() == NodeType::Junction {
+                node.set_switch_pos(JSwitch::JSwitchLeft);
+            }
+        }
+        for (name, position) in clamps {
+            match self.node_map.get_mut(&name) {
+                Some(n) if n.get_node_type() == NodeType::Junction => {
+                    n.set_switch_pos(position);
+                    n.clamped = true;
+                }
+                _ => {
+                    println!("ERROR: {name} is not a junction and cannot be clamped");
+                    self.reset_track_network();
+                    return 22; // EINVAL
+                }
+            }
+        }
+        for name in trailables {
+            if self.set_trailable(&name, true) != 0 {
+                println!("ERROR: {name} is not a junction and cannot be trailable");
+                self.reset_track_network();
+                return 22; // EINVAL
+            }
+        }
+        self.update_all_signals();
+        // The aspects a network starts out with are not changes.
+        self.clear_events();
+        0
+    }
+
+    fn deserialize_track(&mut self, fields: &str) -> i32 {
+        let fields: Vec<&str> = fields.split(',').map(|f| f.trim()).collect();
+        if fields.len() != 8 { return 22; } // EINVAL
+
+        let name = String::from(fields[0]);
+        if name.is_empty() || self.edge_map.contains_key(&name) { return 22; }
+        let mut edge = new_edge(&name);
+        match fields[1].parse() {
+            Ok(n) => edge.length = n,
+            Err(_) => return 22,
+        }
+        for end in [END_A, END_B] {
+            let node_name = String::from(fields[2 + 2 * end]);
+            let slot: Slot = match fields[3 + 2 * end].parse() {
+                Ok(n) if n < NUM_SLOTS => n,
+                _ => return 22,
+            };
+            if node_name.is_empty() { return 22; }
+            if !self.node_map.contains_key(&node_name) {
+                self.create_node(&node_name);
+            }
+            match self.node_map.get_mut(&node_name) {
+                None => return 22,
+                Some(n) => {
+                    if !n.slots[slot].ee_edge.is_empty() {
+                        println!("ERROR: {node_name} slot {slot} is already connected");
+                        return 16; // EBUSY
+                    }
+                    n.set_edge_end(&EdgeEnd { ee_edge: name.clone(), ee_end: end }, slot);
+                }
+            }
+            edge.assign_node_slot(&NodeSlot { ns_node: node_name, ns_slot: slot }, end);
+
+            let sig = if end == END_A { "sigA:" } else { "sigB:" };
+            match fields[6 + end].strip_prefix(sig) {
+                Some("Y") => { edge.place_signal_light(end); }
+                Some("N") => (),
+                _ => return 22,
+            }
+        }
+        self.edge_map.insert(name, edge);
+        0
+    }
+
+    fn deserialize_node(&mut self, fields: &str) -> i32 {
+        let fields: Vec<&str> = fields.split(',').map(|f| f.trim()).collect();
+        if fields.len() != 3 { return 22; } // EINVAL
+
+        let name = String::from(fields[0]);
+        if name.is_empty() { return 22; }
+        let pos = match (fields[1].parse(), fields[2].parse()) {
+            (Ok(x), Ok(y)) => Point { x, y },
+            _ => return 22,
+        };
+        if !self.node_map.contains_key(&name) {
+            self.create_node(&name);
+        }
+        match self.node_map.get_mut(&name) {
+            None => return 22,
+            Some(n) => {
+                if n.fixed_position { return 22; }
+                n.position = Some(pos);
+                n.fixed_position = true;
+            }
+        }
+        0
+    }
+
+    pub fn format_trains(&self) -> String {
+        let mut msg = String::new();
+        for name in self.train_map.keys().sorted() {
+            msg += &self.train_map[name].format(self);
+        }
+        msg
+    }
+
+    // ==============================================================
+    // update_all_signals
+    // ==============================================================
+    // A signal shows red when the track beyond it cannot be entered,
+    // either because the node is a terminator, a junction switch is
+    // set against this edge or stuck, the next edge is closed, or the
+    // block section the next edge is in is detected occupied, or the
+    // signal's lamp is out. Each change of aspect is recorded in the
+    // event log.
     pub fn update_all_signals(&mut self) {
+        let mut aspects = vec![];
+        let occupied = self.segments_in_occupied_blocks();
+        for name in self.edge_map.keys().sorted() {
+            let edge = &self.edge_map[name];
+            for end in [END_A, END_B] {
+                if edge.signals[end].edge.ee_edge.is_empty() { continue; }
+                // A dark signal is taken as red.
+                let red = self.is_lamp_out(name, end) || self.is_path_blocked(edge, end, |s| occupied.contains(s));
+                aspects.push((name.clone(), end, red));
+            }
+        }
+        for (name, end, red) in aspects {
+            let mut changed = false;
+            if let Some(edge) = self.edge_map.get_mut(&name) {
+                let signal = edge.get_signal(end);
+                changed = signal.signal_is_red() != red;
+                signal.set_signal_red(red);
+            }
+            if changed {
+                self.log_event(Event::SignalChanged { segment: name, end, red });
+            }
+        }
+    }
 
+    // ==============================================================
+    // toggle_switch
+    // ==============================================================
+    // Throws a junction switch to its other fork, records it in the
+    // event log and updates the signals. A clamped junction cannot be
+    // thrown, nor can a switch that is stuck or already moving. The
+    // throw is recorded for the safety check with any train standing
+    // on the junction. With a throw time the switch is in motion, and
+    // leads nowhere, until it comes to rest that many ticks later.
+    pub fn toggle_switch(&mut self, name: &String) -> i32 {
+        if self.is_switch_stuck(name) { return 5; } // EIO
+        match self.node_map.get(name) {
+            Some(n) if n.clamped => return 13, // EACCES
+            Some(n) if n.is_in_motion() => return 16, // EBUSY
+            Some(n) if n.get_node_type() == NodeType::Junction => (),
+            Some(_) => return 22, // EINVAL
+            None => return 2, // ENOENT
+        }
+        self.record_throw(name);
+        let position = match self.node_map.get_mut(name) {
+            None => return 2, // ENOENT
+            Some(n) => {
+                n.begin_throw(self.throw_ticks);
+                if n.is_in_motion() { n.moving_to } else { n.get_switch_pos() }
+            }
+        };
+        self.log_event(Event::SwitchThrown { node: name.clone(), position });
+        self.update_all_signals();
+        0
+    }
+
+    // Returns the number of ticks a switch takes to move from one
+    // fork to the other; 0 throws it at once.
+    pub fn get_throw_ticks(&self) -> u32 {
+        self.throw_ticks
+    }
+
+    pub fn set_throw_ticks(&mut self, ticks: u32) {
+        self.throw_ticks = ticks;
+    }
+
+    // ==============================================================
+    // set_reversing
+    // ==============================================================
+    // Designates a segment as one where trains may stop and reverse,
+    // or withdraws that. Trains may always reverse at a terminator.
+    pub fn set_reversing(&mut self, name: &String, reversing: bool) -> i32 {
+        match self.edge_map.get_mut(name) {
+            None => 2, // ENOENT
+            Some(e) => {
+                e.reversing = reversing;
+                0
+            }
+        }
+    }
+
+    // ==============================================================
+    // set_closed
+    // ==============================================================
+    // Takes a segment out of service, or puts it back. The signals
+    // protecting a closed segment show red, and no route enters it.
+    pub fn set_closed(&mut self, name: &String, closed: bool) -> i32 {
+        match self.edge_map.get_mut(name) {
+            None => return 2, // ENOENT
+            Some(e) => e.closed = closed,
+        }
+        self.update_all_signals();
+        0
+    }
+
+    // ==============================================================
+    // set_clamped
+    // ==============================================================
+    // Clamps a junction in its present switch position, or releases
+    // it. Routes through a clamped junction must follow its switch,
+    // and a clamped junction cannot be run through. A switch that is
+    // moving or stuck has no position to be clamped in.
+    pub fn set_clamped(&mut self, name: &String, clamped: bool) -> i32 {
+        if clamped && self.is_switch_stuck(name) { return 16; } // EBUSY
+        match self.node_map.get_mut(name) {
+            Some(n) if n.is_in_motion() => return 16, // EBUSY
+            Some(n) if n.get_node_type() == NodeType::Junction => n.clamped = clamped,
+            Some(_) => return 22, // EINVAL
+            None => return 2, // ENOENT
+        }
+        self.update_all_signals();
+        0
+    }
+
+    // ==============================================================
+    // set_trailable
+    // ==============================================================
+    // Makes a junction trailable, so that a train approaching from the
+    // fork it is not set for runs through it and forces the switch
+    // over, or not, so that the train is held until the switch is
+    // thrown for it.
+    pub fn set_trailable(&mut self, name: &String, trailable: bool) -> i32 {
+        match self.node_map.get_mut(name) {
+            Some(n) if n.get_node_type() == NodeType::Junction => n.trailable = trailable,
+            Some(_) => return 22, // EINVAL
+            None => return 2, // ENOENT
+        }
+        self.update_all_signals();
+        0
+    }
+
+    fn is_path_blocked(&self, edge: &Edge, end: End, occupied: impl Fn(&str) -> bool) -> bool {
+        let ns = edge.get_node(end);
+        let next = match self.node_map.get(&ns.ns_node) {
+            None => return true,
+            Some(n) => {
+                if n.get_node_type() == NodeType::Empty { return true; }
+                n.get_next_through(ns.ns_slot)
+            }
+        };
+        match self.edge_map.get(&next.ee_edge) {
+            None => true,
+            Some(e) => e.closed || occupied(&e.name),
+        }
+    }
+}
+
+// Parses the fields of a "clamp:" line: a junction and the position
+// it is clamped in.
+fn parse_clamp(fields: &str) -> Option<(String, JSwitch)> {
+    let (name, position) = fields.split_once(',')?;
+    let position = match position.trim() {
+        "left" => JSwitch::JSwitchLeft,
+        "right" => JSwitch::JSwitchRight,
+        _ => return None,
+    };
+    let name = name.trim();
+    if name.is_empty() { return None; }
+    Some((String::from(name), position))
+}
+
+fn new_edge(name: &str) -> Edge {
+    Edge {
+        name: String::from(name),
+        length: 1,
+        ends: [ NodeSlot { ns_node: String::new(), ns_slot: SLOT_1, },
+                NodeSlot { ns_node: String::new(), ns_slot: SLOT_1, } ],
+        signals: [ RRsignal { is_red: false, edge:
+                       EdgeEnd { ee_edge: String::new(),
+                                 ee_end: NUM_ENDS } },
+                   RRsignal { is_red: false, edge:
+                       EdgeEnd { ee_edge: String::new(),
+                                 ee_end: NUM_ENDS } } ],
+        train: String::new(),
+        reversing: false,
+        closed: false,
     }
 }
 
@@ -351,9 +894,14 @@ pub fn create_system() -> System {
         edge_map:   HashMap::new(),
         node_map:   HashMap::new(),
         train_map:  HashMap::new(),
+        tick:       0,
+        events:     vec![],
+        timetable:  vec![],
+        cost_weights: CostWeights::default(),
+        reroute_wait: 0,
+        faults:     vec![],
+        safety:     SafetyLog::default(),
+        throw_ticks: 0,
+        auto_switching: false,
     }
 }
-
-pub fn edge_count() -> i32 {
-    return 0;
-}
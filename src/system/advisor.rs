@@ -0,0 +1,210 @@
+// advisor.rs
+//
+// The signal placement advisor. Given the train movements expected
+// over the network, it recommends where signals are missing and
+// where the signals placed do no work:
+//
+//   - a junction a movement runs through from its common edge,
+//     facing the points, wants a signal on the common edge to hold
+//     the train until the switch is set for its route;
+//   - where a movement runs through more segments than the headway,
+//     the ticks between one train and the next, without passing a
+//     signal, the train behind is held for the block to clear, so a
+//     signal is wanted to divide the block after that many segments;
+//   - a signal no movement passes is redundant, as long as another
+//     signal still divides the blocks at its node, or it stands at a
+//     buffer stop with nothing beyond it.
+//
+// Each movement follows the route the planner finds for it now; a
+// movement with no route is left out. The advice is worked out as if
+// each signal recommended were already in place, so a signal added
+// for one movement counts for the next, and the advice can be
+// applied to the network in one go.
+//
+
+use super::common;
+use common::*;
+
+use super::System;
+
+use itertools::Itertools;
+use std::collections::HashSet;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AdviceKind {
+    FacingJunction,
+    LongBlock,
+    Redundant,
+}
+
+impl AdviceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdviceKind::FacingJunction => "facing-junction",
+            AdviceKind::LongBlock => "long-block",
+            AdviceKind::Redundant => "redundant",
+        }
+    }
+}
+
+// A train movement expected over the network: where it starts and
+// the segments it calls at, ending with its destination.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Movement {
+    pub start: String,
+    pub calls: Vec<String>,
+}
+
+// A signal to place, or for a redundant one, to remove.
+#[derive(PartialEq, Clone, Debug)]
+pub struct SignalAdvice {
+    pub kind: AdviceKind,
+    pub segment: String,
+    pub end: End,
+    pub detail: String,
+}
+
+impl SignalAdvice {
+    pub fn is_removal(&self) -> bool {
+        self.kind == AdviceKind::Redundant
+    }
+}
+
+type SignalKey = (String, End);
+
+impl System {
+    // ==============================================================
+    // advise_signals
+    // ==============================================================
+    // Returns the advice on signal placement for the movements given,
+    // with a headway in ticks, or 0 not to check the block lengths.
+    pub fn advise_signals(&self, movements: &[Movement], headway: u32) -> Vec<SignalAdvice> {
+        let routes: Vec<Vec<EdgeEnd>> = movements.iter()
+            .filter_map(|m| self.find_route_via(&m.start, &m.calls))
+            .collect();
+        let placed: Vec<SignalKey> = self.signals_placed();
+        let mut signals: HashSet<SignalKey> = placed.iter().cloned().collect();
+        let mut advice = vec![];
+
+        for route in &routes {
+            for here in crossings(route) {
+                let ns = &self.edge_map[&here.ee_edge].ends[here.ee_end];
+                let facing = ns.ns_slot == SLOT_1 && self.node_map.get(&ns.ns_node)
+                    .is_some_and(|n| n.get_node_type() == NodeType::Junction);
+                if !facing || !signals.insert((here.ee_edge.clone(), here.ee_end)) { continue; }
+                advice.push(SignalAdvice {
+                    kind: AdviceKind::FacingJunction, segment: here.ee_edge.clone(), end: here.ee_end,
+                    detail: format!("{} faces the points of {}", here.ee_edge, ns.ns_node),
+                });
+            }
+        }
+
+        if headway > 0 {
+            for route in &routes {
+                // The segments the route has run through in the block
+                // it is in, and the first of them.
+                let mut run = 1;
+                let mut first = &route[0].ee_edge;
+                for (ix, here) in route.iter().enumerate() {
+                    let Some(next) = route.get(ix + 1) else { break };
+                    if next.ee_edge == here.ee_edge { continue; }
+                    let key = (here.ee_edge.clone(), here.ee_end);
+                    let divided = signals.contains(&key);
+                    if !divided && run < headway {
+                        run += 1;
+                        continue;
+                    }
+                    if !divided {
+                        signals.insert(key);
+                        advice.push(SignalAdvice {
+                            kind: AdviceKind::LongBlock, segment: here.ee_edge.clone(), end: here.ee_end,
+                            detail: format!("the block from {first} runs past the headway of {headway} tick(s)"),
+                        });
+                    }
+                    run = 1;
+                    first = &next.ee_edge;
+                }
+            }
+        }
+
+        let passed: HashSet<SignalKey> = routes.iter()
+            .flat_map(|r| crossings(r))
+            .map(|ee| (ee.ee_edge.clone(), ee.ee_end))
+            .collect();
+        for key in placed {
+            if passed.contains(&key) { continue; }
+            let node = self.edge_map[&key.0].ends[key.1].ns_node.clone();
+            let buffer = self.node_map.get(&node)
+                .is_none_or(|n| matches!(n.get_node_type(), NodeType::Empty | NodeType::Terminator));
+            signals.remove(&key);
+            if !buffer && !self.has_signal_at(&signals, &node) {
+                signals.insert(key);
+                continue;
+            }
+            advice.push(SignalAdvice {
+                kind: AdviceKind::Redundant, segment: key.0, end: key.1,
+                detail: if buffer { format!("it stands at the buffer stop {node}") }
+                        else { String::from("no movement passes it") },
+            });
+        }
+        advice
+    }
+
+    // Places and removes the signals as advised, and returns how many
+    // were changed.
+    pub fn apply_signal_advice(&mut self, advice: &[SignalAdvice]) -> usize {
+        let mut changed = 0;
+        for a in advice {
+            let Some(edge) = self.edge_map.get_mut(&a.segment) else { continue };
+            if edge.has_signal(a.end) != a.is_removal() { continue; }
+            let rc = if a.is_removal() { edge.remove_signal_light(a.end) }
+                     else { edge.place_signal_light(a.end) };
+            if rc == 0 { changed += 1; }
+        }
+        self.update_all_signals();
+        changed
+    }
+
+    // Returns the movements of the services in the timetable.
+    pub fn timetable_movements(&self) -> Vec<Movement> {
+        self.timetable.iter()
+            .map(|s| Movement { start: s.entry.clone(),
+                                calls: s.calls.iter().map(|c| c.segment.clone()).collect() })
+            .collect()
+    }
+
+    // The signals on the network, in segment order.
+    fn signals_placed(&self) -> Vec<SignalKey> {
+        self.edge_map.keys().sorted()
+            .flat_map(|n| [END_A, END_B].map(|end| (n.clone(), end)))
+            .filter(|(n, end)| self.edge_map[n].has_signal(*end))
+            .collect()
+    }
+
+    fn has_signal_at(&self, signals: &HashSet<SignalKey>, node: &str) -> bool {
+        self.node_map.get(node).is_some_and(|n| n.slots.iter()
+            .any(|ee| signals.contains(&(ee.ee_edge.clone(), ee.ee_end))))
+    }
+}
+
+// The steps of a route from which it crosses a node into another
+// segment, which is where it passes any signal.
+fn crossings(route: &[EdgeEnd]) -> impl Iterator<Item = &EdgeEnd> {
+    route.windows(2).filter(|w| w[0].ee_edge != w[1].ee_edge).map(|w| &w[0])
+}
+
+// Formats the advice, one signal to a line.
+pub fn format_advice(advice: &[SignalAdvice]) -> String {
+    if advice.is_empty() {
+        return String::from("No change to the signals is advised\n");
+    }
+    let mut msg = String::from("Signal advice:\n");
+    for a in advice {
+        let at = format!("{} end {}", a.segment, if a.end == END_A { "A" } else { "B" });
+        msg += &format!("  {:<8}{:<15}{:<17}{}\n",
+                        if a.is_removal() { "remove" } else { "place" }, at, a.kind.name(), a.detail);
+    }
+    let removals = advice.iter().filter(|a| a.is_removal()).count();
+    msg += &format!("TOTAL: {} to place, {} to remove\n", advice.len() - removals, removals);
+    msg
+}
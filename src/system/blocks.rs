@@ -0,0 +1,145 @@
+// blocks.rs
+//
+// Block sections. A block is the stretch of track from one signal to
+// the next: the segments joined at nodes where no signal stands,
+// through continuation nodes and through junctions alike. Where a
+// segment has a signal at one end, the node there is a boundary
+// between the block of that segment and the blocks of the segments
+// beyond it.
+//
+// The block is the unit of occupancy for the signals: a signal shows
+// red while any segment in the block beyond it is detected occupied.
+// Blocks are worked out afresh from the track and the signals placed
+// on it, so they follow any change to either. They are named block001,
+// block002 and so on in the order of the first segment in each.
+//
+
+use super::detection::Detection;
+use super::System;
+
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct BlockSection {
+    pub name: String,
+    // The segments in the block, in name order.
+    pub segments: Vec<String>,
+}
+
+// The block sections with the block each segment is in, for looking
+// up blocks one at a time while the trains move.
+pub(super) struct BlockIndex {
+    blocks: Vec<BlockSection>,
+    block_of: HashMap<String, usize>,
+}
+
+impl System {
+    // ==============================================================
+    // get_block_sections
+    // ==============================================================
+    // Returns the block sections of the network in name order.
+    pub fn get_block_sections(&self) -> Vec<BlockSection> {
+        let names: Vec<&String> = self.edge_map.keys().sorted().collect();
+        let index: HashMap<&String, usize> = names.iter().enumerate().map(|(ix, n)| (*n, ix)).collect();
+        let mut parent: Vec<usize> = (0..names.len()).collect();
+
+        for node in self.node_map.values() {
+            // The segment ends at the node with no signal on them.
+            let open: Vec<usize> = node.slots.iter()
+                .filter(|ee| !ee.ee_edge.is_empty())
+                .filter(|ee| self.edge_map.get(&ee.ee_edge).is_some_and(|e| !e.has_signal(ee.ee_end)))
+                .filter_map(|ee| index.get(&ee.ee_edge).copied())
+                .collect();
+            // Any signal at the node separates the segments there.
+            let signalled = node.slots.iter().filter(|ee| !ee.ee_edge.is_empty()).count() > open.len();
+            if signalled { continue; }
+            for pair in open.windows(2) {
+                let (a, b) = (find(&mut parent, pair[0]), find(&mut parent, pair[1]));
+                if a != b { parent[a.max(b)] = a.min(b); }
+            }
+        }
+
+        let mut groups: Vec<Vec<String>> = vec![];
+        let mut group_of: HashMap<usize, usize> = HashMap::new();
+        for (ix, name) in names.iter().enumerate() {
+            let root = find(&mut parent, ix);
+            let group = *group_of.entry(root).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[group].push((*name).clone());
+        }
+        groups.into_iter().enumerate()
+            .map(|(ix, segments)| BlockSection { name: format!("block{:03}", ix + 1), segments })
+            .collect()
+    }
+
+    // Returns the block section a segment is in.
+    pub fn get_block_of(&self, segment: &str) -> Option<BlockSection> {
+        self.get_block_sections().into_iter().find(|b| b.segments.iter().any(|s| s == segment))
+    }
+
+    // Returns the block sections indexed by segment. The blocks follow
+    // the track and signals, not the trains, so the index holds while
+    // trains move.
+    pub(super) fn index_blocks(&self) -> BlockIndex {
+        let blocks = self.get_block_sections();
+        let block_of = blocks.iter().enumerate()
+            .flat_map(|(ix, b)| b.segments.iter().map(move |s| (s.clone(), ix)))
+            .collect();
+        BlockIndex { blocks, block_of }
+    }
+
+    // Returns true if the block a segment is in is detected occupied.
+    pub(super) fn is_block_occupied(&self, index: &BlockIndex, segment: &str) -> bool {
+        index.block_of.get(segment)
+            .is_some_and(|&ix| self.get_block_detection(&index.blocks[ix]) == Detection::Occupied)
+    }
+
+    // Returns what the detectors of a block report together: occupied
+    // if any of its segments is.
+    pub fn get_block_detection(&self, block: &BlockSection) -> Detection {
+        if block.segments.iter().any(|s| self.is_detected_occupied(s)) { Detection::Occupied }
+        else { Detection::Clear }
+    }
+
+    // Returns the segments that are in a block detected occupied, for
+    // working out the signal aspects all at once.
+    pub(super) fn segments_in_occupied_blocks(&self) -> HashSet<String> {
+        self.get_block_sections().into_iter()
+            .filter(|b| self.get_block_detection(b) == Detection::Occupied)
+            .flat_map(|b| b.segments)
+            .collect()
+    }
+
+    // Formats the block sections, one per line with their segments and
+    // the trains in them.
+    pub fn format_blocks(&self) -> String {
+        let blocks = self.get_block_sections();
+        let mut msg = String::new();
+        for block in &blocks {
+            let trains: Vec<&str> = block.segments.iter()
+                .filter_map(|s| self.edge_map.get(s))
+                .filter(|e| !e.train.is_empty())
+                .map(|e| e.train.as_str())
+                .collect();
+            msg += &format!("{:>12}: {}", block.name, block.segments.join(", "));
+            if self.get_block_detection(block) == Detection::Occupied {
+                msg += " (occupied";
+                if !trains.is_empty() { msg += &format!(" by {}", trains.join(", ")); }
+                msg += ")";
+            }
+            msg += "\n";
+        }
+        msg += &format!("TOTAL: {} block sections\n", blocks.len());
+        msg
+    }
+}
+
+fn find(parent: &mut [usize], ix: usize) -> usize {
+    let mut root = ix;
+    while parent[root] != root { root = parent[root]; }
+    parent[ix] = root;
+    root
+}
@@ -0,0 +1,72 @@
+// detection.rs
+//
+// The train detection layer. Signals, the movement of trains and the
+// route planner do not see the trains themselves: each track segment
+// has a detector, a track circuit or axle counter section, that
+// reports it clear or occupied. A detector normally reports what is
+// there, but a detector fault overrides it:
+//
+//   - a detector falsely occupied reports a train that is not there,
+//     which fails safe, as the signals protecting it show red;
+//   - a detector failed clear reports no train when there is one,
+//     which is a wrong-side failure: the signals behind the train may
+//     clear, and a train let in after it is caught by the safety
+//     check.
+//
+// Each segment has its own detector. A block section, the stretch of
+// track between one signal and the next, may span several segments,
+// and what its detectors report together is worked out in blocks.rs:
+// a block is occupied if any of its segments is, and that is what the
+// signals act on.
+//
+
+use super::System;
+
+use itertools::Itertools;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Detection {
+    Clear,
+    Occupied,
+}
+
+impl Detection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Detection::Clear => "clear",
+            Detection::Occupied => "occupied",
+        }
+    }
+}
+
+impl System {
+    // ==============================================================
+    // get_detection
+    // ==============================================================
+    // Returns what the detector of a segment reports, or None if there
+    // is no such segment.
+    pub fn get_detection(&self, segment: &str) -> Option<Detection> {
+        let edge = self.edge_map.get(segment)?;
+        if self.is_falsely_occupied(segment) { return Some(Detection::Occupied); }
+        if self.is_falsely_clear(segment) { return Some(Detection::Clear); }
+        Some(if edge.train.is_empty() { Detection::Clear } else { Detection::Occupied })
+    }
+
+    pub(super) fn is_detected_occupied(&self, segment: &str) -> bool {
+        self.get_detection(segment) == Some(Detection::Occupied)
+    }
+
+    // Formats the detector of each segment that reports occupied, or
+    // that disagrees with what is really there.
+    pub fn format_detection(&self) -> String {
+        let mut msg = format!("{:<12}{:<10}{}\n", "Segment", "Detector", "Train");
+        for name in self.edge_map.keys().sorted() {
+            let train = &self.edge_map[name].train;
+            let detection = self.get_detection(name).unwrap_or(Detection::Clear);
+            if detection == Detection::Clear && train.is_empty() { continue; }
+            msg += &format!("{:<12}{:<10}{}\n", name, detection.name(),
+                            if train.is_empty() { "-" } else { train });
+        }
+        msg
+    }
+}
@@ -13,9 +13,14 @@ use common::JSwitch::JSwitchRight;
 pub struct Edge
 {
     pub name: String,
+    pub length: u32,
     pub ends: [NodeSlot; NUM_ENDS],
     pub signals: [RRsignal; NUM_ENDS],
     pub train: String,
+    // Trains may stop and reverse direction on this segment.
+    pub reversing: bool,
+    // A closed segment is out of service and may not be entered.
+    pub closed: bool,
 }
 
 impl Edge {
@@ -33,7 +38,24 @@ impl Edge {
             return 0;
         }
         println!("ERROR: Signal has already been placed here");
-        return 1;
+        1
+    }
+
+    pub fn remove_signal_light(&mut self, end: End) -> i32 {
+        assert!(end == END_A || end == END_B);
+        if !self.signals[end].edge.ee_edge.is_empty() {
+            self.signals[end].edge.ee_edge.clear();
+            self.signals[end].edge.ee_end = NUM_ENDS;
+            self.signals[end].set_signal_red(false);
+            return 0;
+        }
+        println!("ERROR: No signal has been placed here");
+        1
+    }
+
+    pub fn has_signal(&self, end: End) -> bool {
+        assert!(end == END_A || end == END_B);
+        !self.signals[end].edge.ee_edge.is_empty()
     }
 
     pub fn get_train(&self) -> &String {
@@ -71,19 +93,53 @@ impl Edge {
         self.ends[node_end].ns_slot = node.ns_slot;
     }
 
-    pub fn show(&self, sys: &system::System, show_end: End) {
+    // Formats a one line picture of the edge, its neighbors through
+    // the nodes at either end (or only at `show_end`, unless that is
+    // NUM_ENDS), its signals and any train on it.
+    pub fn format(&self, sys: &system::System, show_end: End) -> String {
         let edge_name = &self.name;
         let mut msg = String::new();
-        let show_edge;
-        match sys.edge_map.get(edge_name) {
-            None => return,
-            Some(e) => show_edge = e,
+        if !sys.edge_map.contains_key(edge_name) {
+            return msg;
         }
 
         if (show_end == END_A) || (show_end == NUM_ENDS) {
-            let node = &show_edge.ends[END_A];
+            match self.format_connection(sys, END_A) {
+                None => return String::from("ERROR: Edge has null end node\n"),
+                Some(s) => msg += &s,
+            }
+            msg += self.signal_glyph(END_A);
+            msg += " ";
+        }
+
+        msg += edge_name;
+
+        if (show_end == END_B) || (show_end == NUM_ENDS) {
+            msg += " ";
+            msg += self.signal_glyph(END_B);
+            match self.format_connection(sys, END_B) {
+                None => return String::from("ERROR: Edge has null end node\n"),
+                Some(s) => msg += &s,
+            }
+        }
+        msg += &self.format_train(sys);
+        if self.reversing { msg += " (reversing)"; }
+        if self.closed { msg += " (closed)"; }
+
+        msg += "\n";
+        msg
+    }
+
+    // Formats the connection through the node at one end of the edge:
+    // the neighboring edge and how the node joins them. The text for
+    // END_A reads leftward into the edge and the text for END_B reads
+    // rightward out of it. Returns None if the end has no node.
+    pub fn format_connection(&self, sys: &system::System, end: End) -> Option<String> {
+        let mut msg = String::new();
+        if end == END_A {
+            let node = &self.ends[END_A];
             match sys.node_map.get(&node.ns_node) {
-                None => { println!("ERROR: Edge has null end node"); return },
+                None => return None,
                 Some(n) => {
                     match n.get_node_type() {
                         NodeType::Empty => {
@@ -127,25 +183,11 @@ impl Edge {
                     }
                 }
             }
-            if self.signals[END_A].edge.ee_edge.is_empty() {
-                msg += "_ ";
-            }
-            else if self.signals[END_A].signal_is_red() { msg += "R "; }
-            else                                        { msg += "G "; }
         }
-
-        msg += edge_name;
-
-        if (show_end == END_B) || (show_end == NUM_ENDS) {
-            if self.signals[END_B].edge.ee_edge.is_empty() {
-                msg += " _";
-            }
-            else if self.signals[END_B].signal_is_red() { msg += " R"; }
-            else                                        { msg += " G"; }
-
-            let node = &show_edge.ends[END_B];
+        else {
+            let node = &self.ends[END_B];
             match sys.node_map.get(&node.ns_node) {
-                None => { println!("ERROR: Edge has null end node"); return },
+                None => return None,
                 Some(n) => {
                     match n.get_node_type() {
                         NodeType::Empty => {
@@ -190,6 +232,20 @@ impl Edge {
                 }
             }
         }
+        Some(msg)
+    }
+
+    // Returns "_" where no signal is placed, otherwise "R" or "G".
+    pub fn signal_glyph(&self, end: End) -> &'static str {
+        if self.signals[end].edge.ee_edge.is_empty()    { "_" }
+        else if self.signals[end].signal_is_red()       { "R" }
+        else                                            { "G" }
+    }
+
+    // Formats the train on the edge, pointing in its direction of
+    // travel, or nothing if the edge is clear.
+    pub fn format_train(&self, sys: &system::System) -> String {
+        let mut msg = String::new();
         if !self.train.is_empty() {
             if let Some(tref) = sys.get_train(&self.train) {
                 let tpos = tref.get_position();
@@ -198,7 +254,6 @@ impl Edge {
                 msg += tref.name.as_str();
             }
         }
-
-        println!("{msg}");
+        msg
     }
 }
@@ -12,4 +12,7 @@ impl RRsignal {
     pub fn signal_is_red(&self) -> bool {
         self.is_red
     }
+    pub fn set_signal_red(&mut self, red: bool) {
+        self.is_red = red;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,227 @@
+// events.rs
+//
+// The simulation event log. Everything of interest that happens to
+// the trains, signals and switches is recorded with the tick it
+// happened in, so scripted runs can be analysed afterwards for
+// throughput and delays. The log can be written out as CSV or as
+// JSON lines.
+//
+// Tick 0 is the time before the first simulation step; each step
+// advances the tick by one. Events from outside a step, such as a
+// switch thrown by hand, carry the tick of the last step taken.
+//
+
+use super::common;
+use common::*;
+
+use super::faults::Fault;
+use super::System;
+
+use std::io::Write;
+
+pub enum Event {
+    // A train was placed on a segment to start a journey.
+    Placed { train: String, segment: String },
+    // A train moved onto a segment.
+    EnteredSegment { train: String, segment: String },
+    // A train could not move on because its signal showed red.
+    HeldAtSignal { train: String, segment: String, end: End },
+    // A junction switch was thrown.
+    SwitchThrown { node: String, position: JSwitch },
+    // A junction switch in motion came to rest.
+    SwitchSettled { node: String, position: JSwitch },
+    // A signal changed aspect.
+    SignalChanged { segment: String, end: End, red: bool },
+    // A train reached its destination.
+    Arrived { train: String, segment: String },
+    // A train reversed its direction of travel on a segment.
+    Reversed { train: String, segment: String },
+    // A timetabled service was cancelled: its train could not be
+    // routed from its entry segment and was taken off the network
+    // again, or the entry segment never cleared for it to depart.
+    Cancelled { train: String, segment: String },
+    // A held train was given another route from the segment it is on.
+    Rerouted { train: String, segment: String },
+    // A train ran through a trailable junction set against it and
+    // forced the switch over.
+    Trailed { train: String, node: String },
+    // A train was forced through a junction set against it and came
+    // off the track.
+    Derailed { train: String, node: String },
+    // A fault was put into effect, or cleared.
+    FaultInjected { fault: Fault },
+    FaultCleared { fault: Fault },
+}
+
+pub struct LogEntry {
+    pub tick: u32,
+    pub event: Event,
+}
+
+const CSV_COLUMNS: [&str; 5] = ["train", "segment", "end", "node", "state"];
+
+fn end_name(end: End) -> String {
+    String::from(if end == END_A { "A" } else { "B" })
+}
+
+// Quotes a CSV field as RFC 4180 asks when it holds a comma, a quote
+// or a line break, doubling any quotes inside.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        String::from(text)
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut rval = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => rval += "\\\"",
+            '\\' => rval += "\\\\",
+            c if (c as u32) < 0x20 => rval += &format!("\\u{:04x}", c as u32),
+            c => rval.push(c),
+        }
+    }
+    rval + "\""
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Placed { .. } => "placed",
+            Event::EnteredSegment { .. } => "entered",
+            Event::HeldAtSignal { .. } => "held",
+            Event::SwitchThrown { .. } => "switch",
+            Event::SwitchSettled { .. } => "settled",
+            Event::SignalChanged { .. } => "signal",
+            Event::Arrived { .. } => "arrived",
+            Event::Reversed { .. } => "reversed",
+            Event::Cancelled { .. } => "cancelled",
+            Event::Rerouted { .. } => "rerouted",
+            Event::Trailed { .. } => "trailed",
+            Event::Derailed { .. } => "derailed",
+            Event::FaultInjected { .. } => "fault",
+            Event::FaultCleared { .. } => "repaired",
+        }
+    }
+
+    // Returns the details of the event as (column, value) pairs, in
+    // the order of CSV_COLUMNS.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Event::Placed { train, segment }
+            | Event::EnteredSegment { train, segment }
+            | Event::Arrived { train, segment }
+            | Event::Reversed { train, segment }
+            | Event::Cancelled { train, segment }
+            | Event::Rerouted { train, segment } => {
+                vec![("train", train.clone()), ("segment", segment.clone())]
+            }
+            Event::Trailed { train, node } | Event::Derailed { train, node } => {
+                vec![("train", train.clone()), ("node", node.clone())]
+            }
+            Event::HeldAtSignal { train, segment, end } => {
+                vec![("train", train.clone()), ("segment", segment.clone()),
+                     ("end", end_name(*end))]
+            }
+            Event::SwitchThrown { node, position } | Event::SwitchSettled { node, position } => {
+                let state = match position {
+                    JSwitch::JSwitchLeft => "left",
+                    JSwitch::JSwitchRight => "right",
+                    JSwitch::JSwitchNone => "none",
+                };
+                vec![("node", node.clone()), ("state", String::from(state))]
+            }
+            Event::SignalChanged { segment, end, red } => {
+                vec![("segment", segment.clone()), ("end", end_name(*end)),
+                     ("state", String::from(if *red { "red" } else { "green" }))]
+            }
+            Event::FaultInjected { fault } | Event::FaultCleared { fault } => {
+                let mut fields = match fault {
+                    Fault::SwitchStuck { node, .. } => vec![("node", node.clone())],
+                    Fault::LampOut { segment, end } => {
+                        vec![("segment", segment.clone()), ("end", end_name(*end))]
+                    }
+                    Fault::FalseOccupied { segment } | Fault::FalseClear { segment } => {
+                        vec![("segment", segment.clone())]
+                    }
+                };
+                fields.push(("state", String::from(fault.name())));
+                fields
+            }
+        }
+    }
+}
+
+impl LogEntry {
+    pub fn to_csv(&self) -> String {
+        let fields = self.event.fields();
+        let mut line = format!("{},{}", self.tick, self.event.name());
+        for column in CSV_COLUMNS {
+            line.push(',');
+            if let Some((_, value)) = fields.iter().find(|(c, _)| *c == column) {
+                line += &csv_field(value);
+            }
+        }
+        line
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut line = format!("{{\"tick\":{},\"event\":\"{}\"", self.tick, self.event.name());
+        for (column, value) in self.event.fields() {
+            line += &format!(",\"{column}\":{}", json_string(&value));
+        }
+        line + "}"
+    }
+}
+
+impl System {
+    pub fn get_tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn get_events(&self) -> &Vec<LogEntry> {
+        &self.events
+    }
+
+    pub fn clear_events(&mut self) {
+        self.events.clear();
+    }
+
+    pub(super) fn log_event(&mut self, event: Event) {
+        self.events.push(LogEntry { tick: self.tick, event });
+    }
+
+    // ==============================================================
+    // write_events_csv
+    // ==============================================================
+    // Writes the event log as CSV with a header line. Columns that do
+    // not apply to an event are left empty.
+    pub fn write_events_csv(&self, out: &mut dyn Write) -> i32 {
+        if writeln!(out, "tick,event,{}", CSV_COLUMNS.join(",")).is_err() {
+            return 5; // EIO
+        }
+        for entry in &self.events {
+            if writeln!(out, "{}", entry.to_csv()).is_err() {
+                return 5; // EIO
+            }
+        }
+        0
+    }
+
+    // ==============================================================
+    // write_events_json
+    // ==============================================================
+    // Writes the event log as JSON lines, one object per event with
+    // only the fields that apply to it.
+    pub fn write_events_json(&self, out: &mut dyn Write) -> i32 {
+        for entry in &self.events {
+            if writeln!(out, "{}", entry.to_json()).is_err() {
+                return 5; // EIO
+            }
+        }
+        0
+    }
+}
@@ -0,0 +1,265 @@
+// faults.rs
+//
+// Failure injection for safety analysis. Faults can be injected and
+// cleared at any point in a simulation, and each is recorded in the
+// event log. The signal logic is expected to fail safe:
+//
+//   - a junction switch stuck part way is in JSwitchNone, which
+//     leads nowhere, so the signals approaching it show red and it
+//     cannot be thrown until the fault is cleared;
+//   - a signal with its lamp out is dark, and a dark signal is taken
+//     to be red;
+//   - a detector falsely reporting a segment as occupied makes the
+//     signals protecting it show red, and trains will not enter it,
+//     as if a train were there.
+//
+// A detector failed clear, reporting no train where there is one, is
+// the exception: it fails to the wrong side, and it is left to the
+// safety check to catch a train let in behind another (see
+// detection.rs).
+// The fault report lists the faults in effect and the trains whose
+// remaining route runs into them.
+//
+
+use super::common;
+use common::*;
+
+use super::events::Event;
+use super::System;
+
+use itertools::Itertools;
+
+#[derive(Clone, Debug)]
+pub enum Fault {
+    // `position` is where the switch stood before it stuck, which it
+    // goes back to when the fault is cleared. It is filled in when the
+    // fault is injected, so any value may be given.
+    SwitchStuck { node: String, position: JSwitch },
+    LampOut { segment: String, end: End },
+    FalseOccupied { segment: String },
+    FalseClear { segment: String },
+}
+
+// Faults are the same when they are at the same place: a stuck switch
+// is known by its node alone.
+impl PartialEq for Fault {
+    fn eq(&self, other: &Fault) -> bool {
+        match (self, other) {
+            (Fault::SwitchStuck { node: a, .. }, Fault::SwitchStuck { node: b, .. }) => a == b,
+            (Fault::LampOut { segment: a, end: x }, Fault::LampOut { segment: b, end: y }) => {
+                a == b && x == y
+            }
+            (Fault::FalseOccupied { segment: a }, Fault::FalseOccupied { segment: b })
+            | (Fault::FalseClear { segment: a }, Fault::FalseClear { segment: b }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Fault {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fault::SwitchStuck { .. } => "switch-stuck",
+            Fault::LampOut { .. } => "lamp-out",
+            Fault::FalseOccupied { .. } => "false-occupied",
+            Fault::FalseClear { .. } => "false-clear",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Fault::SwitchStuck { node, .. } => format!("switch stuck at {node}"),
+            Fault::LampOut { segment, end } => {
+                format!("lamp out at {segment} end {}", if *end == END_A { "A" } else { "B" })
+            }
+            Fault::FalseOccupied { segment } => format!("{segment} falsely occupied"),
+            Fault::FalseClear { segment } => format!("{segment} falsely clear"),
+        }
+    }
+}
+
+pub struct AffectedTrain {
+    pub train: String,
+    pub fault: Fault,
+}
+
+impl System {
+    // ==============================================================
+    // inject_fault
+    // ==============================================================
+    // Puts a fault into effect. Returns 2 if the node or segment does
+    // not exist, 22 if a stuck switch is not at a junction or a lamp
+    // is not at a signal, 16 if a switch to stick is clamped, and 17
+    // if the fault is already in effect.
+    pub fn inject_fault(&mut self, mut fault: Fault) -> i32 {
+        match &fault {
+            Fault::SwitchStuck { node, .. } => {
+                match self.node_map.get(node) {
+                    None => return 2, // ENOENT
+                    Some(n) => {
+                        if n.get_node_type() != NodeType::Junction { return 22; } // EINVAL
+                        // Clamped, it cannot move, and would lose the
+                        // position it is clamped in.
+                        if n.clamped { return 16; } // EBUSY
+                    }
+                }
+            }
+            Fault::LampOut { segment, end } => {
+                match self.edge_map.get(segment) {
+                    None => return 2, // ENOENT
+                    Some(e) => if *end >= NUM_ENDS || !e.has_signal(*end) { return 22; } // EINVAL
+                }
+            }
+            Fault::FalseOccupied { segment } | Fault::FalseClear { segment } => {
+                if !self.edge_map.contains_key(segment) { return 2; } // ENOENT
+            }
+        }
+        if self.faults.contains(&fault) { return 17; } // EEXIST
+
+        if let Fault::SwitchStuck { node, position } = &mut fault {
+            if let Some(n) = self.node_map.get_mut(node.as_str()) {
+                // A switch in motion still stands where it set off from.
+                *position = match n.moving_to {
+                    _ if !n.is_in_motion() => n.get_switch_pos(),
+                    JSwitch::JSwitchLeft => JSwitch::JSwitchRight,
+                    _ => JSwitch::JSwitchLeft,
+                };
+                n.stop_throw();
+                n.set_switch_pos(JSwitch::JSwitchNone);
+            }
+        }
+        self.faults.push(fault.clone());
+        self.log_event(Event::FaultInjected { fault });
+        self.update_all_signals();
+        0
+    }
+
+    // ==============================================================
+    // clear_fault
+    // ==============================================================
+    // Takes a fault out of effect. A switch that was stuck goes back
+    // to where it stood before, or to the left fork if it had no
+    // position, and is recorded as thrown. Returns 2 if the fault is
+    // not in effect, and 16 if a train stands on the stuck switch.
+    pub fn clear_fault(&mut self, fault: &Fault) -> i32 {
+        let ix = match self.faults.iter().position(|f| f == fault) {
+            None => return 2, // ENOENT
+            Some(ix) => ix,
+        };
+        let fault = self.faults[ix].clone();
+        if let Fault::SwitchStuck { node, .. } = &fault {
+            if !self.junction_occupants(node).is_empty() { return 16; } // EBUSY
+        }
+        self.faults.remove(ix);
+        self.log_event(Event::FaultCleared { fault: fault.clone() });
+        if let Fault::SwitchStuck { node, position } = fault {
+            let position = if position == JSwitch::JSwitchNone { JSwitch::JSwitchLeft } else { position };
+            self.record_throw(&node);
+            if let Some(n) = self.node_map.get_mut(&node) {
+                n.set_switch_pos(position);
+            }
+            self.log_event(Event::SwitchThrown { node, position });
+        }
+        self.update_all_signals();
+        0
+    }
+
+    pub fn get_faults(&self) -> &Vec<Fault> {
+        &self.faults
+    }
+
+    pub(super) fn is_switch_stuck(&self, node: &str) -> bool {
+        self.faults.iter().any(|f| matches!(f, Fault::SwitchStuck { node: n, .. } if n == node))
+    }
+
+    pub(super) fn is_lamp_out(&self, segment: &str, end: End) -> bool {
+        self.faults.iter().any(|f| {
+            matches!(f, Fault::LampOut { segment: s, end: e } if s == segment && *e == end)
+        })
+    }
+
+    // Returns true if the detector of the segment reports it occupied
+    // whether or not a train is there.
+    pub(super) fn is_falsely_occupied(&self, segment: &str) -> bool {
+        self.faults.iter().any(|f| matches!(f, Fault::FalseOccupied { segment: s } if s == segment))
+    }
+
+    // Returns true if the detector of the segment reports it clear
+    // whether or not a train is there.
+    pub(super) fn is_falsely_clear(&self, segment: &str) -> bool {
+        self.faults.iter().any(|f| matches!(f, Fault::FalseClear { segment: s } if s == segment))
+    }
+
+    // ==============================================================
+    // affected_trains
+    // ==============================================================
+    // Returns each train whose remaining route passes a fault: through
+    // a stuck switch, past a dark signal, or into a segment whose
+    // detector is at fault. A train is listed once for each such fault,
+    // in the order it would meet them.
+    pub fn affected_trains(&self) -> Vec<AffectedTrain> {
+        let mut affected = vec![];
+        for name in self.train_map.keys().sorted() {
+            let train = &self.train_map[name];
+            if train.has_arrived() { continue; }
+            let mut faults: Vec<Fault> = vec![];
+            let mut at = &train.edge;
+            for next in &train.route {
+                if next.ee_edge != at.ee_edge {
+                    faults.extend(self.faults_between(at, next));
+                }
+                at = next;
+            }
+            let mut seen: Vec<Fault> = vec![];
+            for fault in faults {
+                if seen.contains(&fault) { continue; }
+                seen.push(fault.clone());
+                affected.push(AffectedTrain { train: name.clone(), fault });
+            }
+        }
+        affected
+    }
+
+    // Returns the faults met on the move from `pos` onto the next
+    // segment `next`.
+    fn faults_between(&self, pos: &EdgeEnd, next: &EdgeEnd) -> Vec<Fault> {
+        let mut rval = vec![];
+        if self.is_lamp_out(&pos.ee_edge, pos.ee_end) {
+            rval.push(Fault::LampOut { segment: pos.ee_edge.clone(), end: pos.ee_end });
+        }
+        if let Some(e) = self.edge_map.get(&pos.ee_edge) {
+            let node = e.get_node(pos.ee_end).ns_node;
+            let stuck = self.faults.iter()
+                .find(|f| matches!(f, Fault::SwitchStuck { node: n, .. } if *n == node));
+            if let Some(fault) = stuck {
+                rval.push(fault.clone());
+            }
+        }
+        if self.is_falsely_occupied(&next.ee_edge) {
+            rval.push(Fault::FalseOccupied { segment: next.ee_edge.clone() });
+        }
+        if self.is_falsely_clear(&next.ee_edge) {
+            rval.push(Fault::FalseClear { segment: next.ee_edge.clone() });
+        }
+        rval
+    }
+
+    // Formats the faults in effect, each with the trains whose route
+    // runs into it.
+    pub fn format_fault_report(&self) -> String {
+        if self.faults.is_empty() {
+            return String::from("No faults in effect\n");
+        }
+        let affected = self.affected_trains();
+        let mut msg = format!("{:<32}{}\n", "Fault", "Trains affected");
+        for fault in &self.faults {
+            let trains: Vec<&str> = affected.iter()
+                .filter(|a| a.fault == *fault)
+                .map(|a| a.train.as_str())
+                .collect();
+            let trains = if trains.is_empty() { String::from("-") } else { trains.join(", ") };
+            msg += &format!("{:<32}{}\n", fault.describe(), trains);
+        }
+        msg
+    }
+}
@@ -0,0 +1,164 @@
+// layout.rs
+//
+// Automatic schematic layout of the track network. Each node gets a
+// 2D position worked out from the topology alone: the network is
+// walked breadth-first from a starting node, and each segment is laid
+// out along the direction of travel, as long as its length. A
+// continuation carries the line straight on. At a junction the left
+// fork (SLOT_2) is drawn one row to the left of the direction of
+// travel and the right fork (SLOT_3) one row to the right, after
+// which both lines run parallel to the common edge again, the way a
+// railway schematic is drawn.
+//
+// Nodes given fixed coordinates in the network file keep them, and
+// the walk starts from them so the rest of the network is laid out
+// around them. Disconnected parts of the network are stacked below
+// one another.
+//
+
+use super::common;
+use common::*;
+
+use super::System;
+
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+// Rows between disconnected parts of the network.
+const COMPONENT_GAP: f64 = 2.0;
+
+fn grid_cell(pos: Point) -> (i64, i64) {
+    (pos.x.round() as i64, pos.y.round() as i64)
+}
+
+impl System {
+    // ==============================================================
+    // layout_network
+    // ==============================================================
+    // Stores a schematic position on every node that does not have
+    // fixed coordinates, replacing any earlier automatic layout.
+    pub fn layout_network(&mut self) {
+        let mut layout = self.compute_layout();
+        for (name, node) in self.node_map.iter_mut() {
+            if !node.fixed_position { node.position = layout.remove(name); }
+        }
+    }
+
+    // ==============================================================
+    // compute_layout
+    // ==============================================================
+    // Works out a schematic position for every node, keeping those
+    // with fixed coordinates, without changing the network. Positions
+    // use segment lengths along the track and rows across it, with Y
+    // increasing downward.
+    pub fn compute_layout(&self) -> HashMap<String, Point> {
+        let mut layout: HashMap<String, Point> = self.node_map.values()
+            .filter(|n| n.fixed_position)
+            .filter_map(|n| n.position.map(|p| (n.name.clone(), p)))
+            .collect();
+
+        // The direction of travel from each placed node's SLOT_1
+        // toward its other slots: +1.0 is along X, -1.0 against it.
+        let mut heading: HashMap<String, f64> = HashMap::new();
+        let mut occupied: HashSet<(i64, i64)> = layout.values().copied().map(grid_cell).collect();
+
+        let fixed: Vec<String> = layout.keys().sorted().cloned().collect();
+        let starts: Vec<String> = fixed.into_iter()
+            .chain(self.node_map.keys().sorted().cloned())
+            .collect();
+
+        let mut next_row = 0.0;
+        for start in starts {
+            if heading.contains_key(&start) { continue; }
+            let node = match self.node_map.get(&start) {
+                Some(n) => n,
+                None => continue,
+            };
+            if !layout.contains_key(&start) {
+                let pos = Point { x: 0.0, y: next_row };
+                occupied.insert(grid_cell(pos));
+                layout.insert(start.clone(), pos);
+            }
+            // A terminator's only segment leaves through SLOT_1, so
+            // face it backward to draw the line left to right.
+            let fwd = if node.get_node_type() == NodeType::Terminator { -1.0 } else { 1.0 };
+            heading.insert(start.clone(), fwd);
+            self.layout_from(&start, &mut layout, &mut heading, &mut occupied);
+
+            let bottom = layout.values().map(|p| p.y).fold(next_row, f64::max);
+            next_row = bottom + COMPONENT_GAP;
+        }
+        layout
+    }
+
+    // Places every node reachable from `start`, which must already
+    // have a position and heading.
+    fn layout_from(&self, start: &str,
+                   layout: &mut HashMap<String, Point>,
+                   heading: &mut HashMap<String, f64>,
+                   occupied: &mut HashSet<(i64, i64)>) {
+        let mut queue = VecDeque::from([String::from(start)]);
+        while let Some(name) = queue.pop_front() {
+            let node = match self.node_map.get(&name) {
+                Some(n) => n,
+                None => continue,
+            };
+            let from = layout[&name];
+            let fwd = heading[&name];
+            let is_junction = node.get_node_type() == NodeType::Junction;
+
+            for slot in [SLOT_1, SLOT_2, SLOT_3] {
+                let ee = &node.slots[slot];
+                if ee.ee_edge.is_empty() { continue; }
+                let (far, length) = match self.edge_map.get(&ee.ee_edge) {
+                    Some(e) => (e.get_adjacent(ee.ee_end), e.length.max(1) as f64),
+                    None => continue,
+                };
+                if heading.contains_key(&far.ns_node) { continue; }
+
+                // Leaving through SLOT_1 runs against the heading.
+                let dx = if slot == SLOT_1 { -fwd } else { fwd };
+                // Left of the direction of travel is up the page.
+                let side = match (is_junction, slot) {
+                    (true, SLOT_2) => -1.0,
+                    (true, SLOT_3) => 1.0,
+                    _ => 0.0,
+                } * fwd;
+
+                // Arriving at the far node's SLOT_1 means travelling
+                // the same way as its heading; at a fork, against it.
+                heading.insert(far.ns_node.clone(),
+                               if far.ns_slot == SLOT_1 { dx } else { -dx });
+                queue.push_back(far.ns_node.clone());
+
+                if layout.contains_key(&far.ns_node) { continue; }
+                let mut pos = Point { x: from.x + dx * length, y: from.y + side };
+                // Step further across the track until the spot is free.
+                let step = if side == 0.0 { 1.0 } else { side };
+                while occupied.contains(&grid_cell(pos)) {
+                    pos.y += step;
+                }
+                occupied.insert(grid_cell(pos));
+                layout.insert(far.ns_node.clone(), pos);
+            }
+        }
+    }
+
+    // ==============================================================
+    // set_node_position
+    // ==============================================================
+    // Fixes a node at the given schematic coordinates, or releases it
+    // back to the automatic layout when `pos` is None.
+    pub fn set_node_position(&mut self, name: &String, pos: Option<Point>) -> i32 {
+        match self.node_map.get_mut(name) {
+            None => 2, // ENOENT
+            Some(node) => {
+                node.position = pos;
+                node.fixed_position = pos.is_some();
+                0
+            }
+        }
+    }
+}
@@ -13,6 +13,17 @@ pub struct Node
     pub name: String,
     pub slots: [EdgeEnd; NUM_SLOTS],
     pub switch_state: JSwitch,
+    pub position: Option<Point>,
+    pub fixed_position: bool,
+    // A clamped junction is locked in its switch position.
+    pub clamped: bool,
+    // A trailable junction lets a train approaching from the fork it
+    // is not set for run through, forcing the switch over.
+    pub trailable: bool,
+    // A switch being thrown is in JSwitchNone for the ticks remaining
+    // before it comes to rest in the position it is moving to.
+    pub moving_to: JSwitch,
+    pub throw_remaining: u32,
 }
 
 impl Node {
@@ -49,7 +60,7 @@ impl Node {
         if ! self.slots[SLOT_3].ee_edge.is_empty() { return NodeType::Junction; }
         if ! self.slots[SLOT_2].ee_edge.is_empty() { return NodeType::Continuation; }
         if ! self.slots[SLOT_1].ee_edge.is_empty() { return NodeType::Terminator; }
-        return NodeType::Empty;
+        NodeType::Empty
     }
 
     pub fn get_edge_end(&self, slot: Slot) -> EdgeEnd {
@@ -123,7 +134,7 @@ impl Node {
                         }
                     }
                     JSwitch::JSwitchNone => {
-                        // Should never happen (panic?)
+                        // Stuck or in motion, so no way through (return empty).
                         rval = EdgeEnd { ee_edge: String::new(), ee_end: NUM_ENDS }
                     }
                 }
@@ -132,10 +143,29 @@ impl Node {
         rval
     }
 
-    pub fn get_switch_pos(&self) -> JSwitch {
-        let rval = self.switch_state;
+    // Returns where a train at `slot` goes on to, as get_next does,
+    // except that a train trailing through a trailable junction set
+    // for the other fork runs through to the common edge. A clamped
+    // junction cannot be run through, nor a switch stuck or moving.
+    pub fn get_next_through(&self, slot: Slot) -> EdgeEnd {
+        let rval = self.get_next(slot);
+        if rval.ee_edge.is_empty() && self.is_run_through(slot) {
+            return self.get_edge_end(SLOT_1);
+        }
         rval
     }
+
+    // Returns true if a train at `slot` trails through the junction
+    // from the fork it is not set for, and it is free to be forced.
+    pub fn is_run_through(&self, slot: Slot) -> bool {
+        self.get_node_type() == NodeType::Junction && self.trailable && !self.clamped
+            && slot != SLOT_1 && self.switch_state != JSwitchNone
+            && self.get_next(slot).ee_edge.is_empty()
+    }
+
+    pub fn get_switch_pos(&self) -> JSwitch {
+        self.switch_state
+    }
     pub fn set_switch_pos(&mut self, jsw: JSwitch) {
         self.switch_state = jsw;
     }
@@ -145,7 +175,43 @@ impl Node {
             else                                { JSwitchLeft  };
     }
 
-    pub fn show(&self, sys: &System) {
+    // Starts the switch moving to its other fork, which it reaches
+    // after the given number of ticks, or at once for none.
+    pub fn begin_throw(&mut self, ticks: u32) {
+        let target = if self.switch_state == JSwitchLeft { JSwitchRight } else { JSwitchLeft };
+        if ticks == 0 {
+            self.switch_state = target;
+            return;
+        }
+        self.switch_state = JSwitchNone;
+        self.moving_to = target;
+        self.throw_remaining = ticks;
+    }
+
+    // Moves a switch in motion on by one tick, and returns true if it
+    // has come to rest.
+    pub fn advance_throw(&mut self) -> bool {
+        if self.throw_remaining == 0 { return false; }
+        self.throw_remaining -= 1;
+        if self.throw_remaining > 0 { return false; }
+        self.switch_state = self.moving_to;
+        self.moving_to = JSwitchNone;
+        true
+    }
+
+    // Stops a switch in motion where it is, leaving it in JSwitchNone.
+    pub fn stop_throw(&mut self) {
+        self.moving_to = JSwitchNone;
+        self.throw_remaining = 0;
+    }
+
+    pub fn is_in_motion(&self) -> bool {
+        self.throw_remaining > 0
+    }
+
+    // Formats the node name, the nodes at the far end of each
+    // connected edge and, for a junction, the switch position.
+    pub fn format(&self, sys: &System) -> String {
         let mut nstr = String::new();
         let mut edge = &self.slots[0].ee_edge;
         nstr += format!("{:>12}:", self.name).as_str();
@@ -168,11 +234,13 @@ impl Node {
         if !edge.is_empty() {
             nstr += format!("{:>9}", "(switch").as_str();
             match &self.switch_state {
+                JSwitchNone if self.is_in_motion() => nstr += ": moving)",
                 JSwitchNone  => nstr += ": none)",
                 JSwitchLeft  => nstr += ": left)",
                 JSwitchRight => nstr += ": right)",
             }
         }
-        println!("{nstr}");
-        }
+        nstr += "\n";
+        nstr
+    }
 }
@@ -0,0 +1,71 @@
+// replay.rs
+//
+// Recording of a simulation run. The state of everything that can
+// change while the simulation runs -- where each train is, what each
+// signal shows and how each junction switch is set -- is captured
+// before the first step and after every step, so the run can be
+// played back later, for example as an animated SVG.
+//
+
+use super::common;
+use common::*;
+
+use super::System;
+
+use itertools::Itertools;
+
+pub struct TickState {
+    pub tick: u32,
+    // Each train and the segment it is on, by train name.
+    pub trains: Vec<(String, String)>,
+    // Each placed signal as (segment, end, is red), by segment name.
+    pub signals: Vec<(String, End, bool)>,
+    // Each junction and its switch position, by node name.
+    pub switches: Vec<(String, JSwitch)>,
+}
+
+pub struct Replay {
+    // One state per tick, starting with the state before the run.
+    pub frames: Vec<TickState>,
+}
+
+impl System {
+    // ==============================================================
+    // capture_state
+    // ==============================================================
+    // Returns the current train positions, signal aspects and switch
+    // positions, labelled with the given tick.
+    pub fn capture_state(&self, tick: u32) -> TickState {
+        let trains = self.train_map.keys().sorted()
+            .map(|t| (t.clone(), self.train_map[t].get_position().ee_edge.clone()))
+            .collect();
+        let mut signals = vec![];
+        for name in self.edge_map.keys().sorted() {
+            let edge = &self.edge_map[name];
+            for end in [END_A, END_B] {
+                if edge.has_signal(end) {
+                    signals.push((name.clone(), end, edge.signals[end].signal_is_red()));
+                }
+            }
+        }
+        let switches = self.get_all_junctions().into_iter()
+            .map(|j| { let pos = self.node_map[&j].get_switch_pos(); (j, pos) })
+            .collect();
+        TickState { tick, trains, signals, switches }
+    }
+
+    // ==============================================================
+    // run_recorded
+    // ==============================================================
+    // Runs the simulation to completion like run, and returns the
+    // state at the start and after every step in which a train moved.
+    pub fn run_recorded(&mut self) -> Replay {
+        let mut frames = vec![self.capture_state(0)];
+        let mut tick = 0;
+        while self.step() > 0 {
+            tick += 1;
+            frames.push(self.capture_state(tick));
+        }
+        Replay { frames }
+    }
+}
@@ -0,0 +1,456 @@
+// route.rs
+//
+// Route planning for trains. The track network is searched for the
+// cheapest route, but it is not a plain graph: approaching a
+// junction from the common edge a train may take either fork,
+// while from a fork edge the only way on is the common edge.
+// There is never a path from one fork edge to the other without
+// reversing. A train may reverse where its segment ends at a
+// terminator, or anywhere on a segment designated for reversing;
+// in a route a reversal is a step to the same segment heading the
+// other way. Closed segments are never entered, a route through a
+// clamped junction must follow the way its switch is set, and there
+// is no way through a switch that is stuck.
+//
+// The cost of a route is the total length of the segments it enters,
+// plus a cost for each junction it passes through on the straight or
+// the diverging leg, for each reversal, and for each segment it
+// enters that its detector shows occupied, other than by the train
+// itself, at the time of planning. Each is weighted by the system's
+// CostWeights. The left fork of a junction (slot 2), where its switch
+// rests, is taken to be the straight leg.
+//
+
+use super::common;
+use common::*;
+
+use super::events::Event;
+use super::System;
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+// A position on a leg of a route: the segment, the end the train
+// is heading toward, and the number of calls reached so far.
+type Key = (String, End, usize);
+
+// The number of routes considered when rerouting a held train.
+pub const ALTERNATIVE_ROUTES: usize = 3;
+
+// Both ways a train could set off from a segment.
+fn start_positions(start: &str) -> [EdgeEnd; 2] {
+    [EdgeEnd { ee_edge: String::from(start), ee_end: END_B },
+     EdgeEnd { ee_edge: String::from(start), ee_end: END_A }]
+}
+
+// Returns true if the partial route of the label at `ix` has been to
+// `key` already.
+fn label_visits(labels: &[(Key, Option<usize>)], ix: usize, key: &Key) -> bool {
+    let mut at = Some(ix);
+    while let Some(i) = at {
+        if labels[i].0 == *key { return true; }
+        at = labels[i].1;
+    }
+    false
+}
+
+// The weight of each part of the cost of a route.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct CostWeights {
+    // Per unit of segment length.
+    pub length: u32,
+    // Per junction passed through on the straight leg.
+    pub straight: u32,
+    // Per junction passed through on the diverging leg.
+    pub diverging: u32,
+    pub reversal: u32,
+    // Per segment entered that another train is on.
+    pub occupied: u32,
+}
+
+impl Default for CostWeights {
+    fn default() -> CostWeights {
+        CostWeights { length: 1, straight: 0, diverging: 1, reversal: 5, occupied: 10 }
+    }
+}
+
+// What makes up the cost of a route: the total length entered and
+// the number of each kind of costed move.
+#[derive(PartialEq, Clone, Copy, Default, Debug)]
+pub struct RouteCost {
+    pub length: u32,
+    pub straight: u32,
+    pub diverging: u32,
+    pub reversals: u32,
+    pub occupied: u32,
+}
+
+impl RouteCost {
+    // The weights may be as large as a u32 holds, so the cost stops
+    // at u32::MAX rather than overflowing.
+    pub fn total(&self, w: &CostWeights) -> u32 {
+        [(self.length, w.length), (self.straight, w.straight), (self.diverging, w.diverging),
+         (self.reversals, w.reversal), (self.occupied, w.occupied)]
+            .into_iter()
+            .fold(0u32, |sum, (count, weight)| sum.saturating_add(count.saturating_mul(weight)))
+    }
+
+    fn add(&mut self, other: &RouteCost) {
+        self.length = self.length.saturating_add(other.length);
+        self.straight = self.straight.saturating_add(other.straight);
+        self.diverging = self.diverging.saturating_add(other.diverging);
+        self.reversals = self.reversals.saturating_add(other.reversals);
+        self.occupied = self.occupied.saturating_add(other.occupied);
+    }
+
+    // Formats the breakdown as a table of count, weight and cost for
+    // each part, under the total.
+    pub fn format(&self, w: &CostWeights) -> String {
+        let mut msg = format!("Route cost: {}\n", self.total(w));
+        for (name, count, weight) in [("length", self.length, w.length),
+                                      ("straight", self.straight, w.straight),
+                                      ("diverging", self.diverging, w.diverging),
+                                      ("reversals", self.reversals, w.reversal),
+                                      ("occupied", self.occupied, w.occupied)] {
+            msg += &format!("  {:<12}{:>6} x {:<4}{:>6}\n", name, count, weight, count.saturating_mul(weight));
+        }
+        msg
+    }
+}
+
+impl System {
+    // ==============================================================
+    // get_next_moves
+    // ==============================================================
+    // Returns every position a train at `pos` could move to next,
+    // regardless of the current junction switch settings. Each
+    // returned EdgeEnd names the next edge and the end of it the
+    // train will then be heading toward.
+    pub fn get_next_moves(&self, pos: &EdgeEnd) -> Vec<EdgeEnd> {
+        let mut rval = vec![];
+        let ns = match self.edge_map.get(&pos.ee_edge) {
+            None => return rval,
+            Some(e) => e.get_node(pos.ee_end),
+        };
+        let node = match self.node_map.get(&ns.ns_node) {
+            None => return rval,
+            Some(n) => n,
+        };
+        let slots = match node.get_node_type() {
+            NodeType::Continuation => {
+                if ns.ns_slot == SLOT_1 { vec![SLOT_2] } else { vec![SLOT_1] }
+            }
+            NodeType::Junction => {
+                if ns.ns_slot == SLOT_1 { vec![SLOT_2, SLOT_3] } else { vec![SLOT_1] }
+            }
+            _ => vec![],
+        };
+        for slot in slots {
+            let next = node.get_edge_end(slot);
+            // The train enters the next edge at the end attached to
+            // this node, so it is heading toward the opposite end.
+            rval.push(EdgeEnd { ee_edge: next.ee_edge,
+                                ee_end:  opposite_end(next.ee_end) });
+        }
+        rval
+    }
+
+    // ==============================================================
+    // can_reverse
+    // ==============================================================
+    // Returns true if a train at `pos` may reverse: the segment is
+    // designated for reversing, or the end it is heading toward is
+    // a terminator.
+    pub fn can_reverse(&self, pos: &EdgeEnd) -> bool {
+        let edge = match self.edge_map.get(&pos.ee_edge) {
+            None => return false,
+            Some(e) => e,
+        };
+        if edge.reversing { return true; }
+        match self.node_map.get(&edge.get_node(pos.ee_end).ns_node) {
+            None => false,
+            Some(n) => n.get_node_type() == NodeType::Terminator,
+        }
+    }
+
+    // ==============================================================
+    // find_route
+    // ==============================================================
+    // Finds the cheapest route from the start edge to the destination
+    // edge. The train may set off in either direction, so the first
+    // entry of the returned route is the start edge with the chosen
+    // direction of travel, and the last entry is on the destination
+    // edge.
+    pub fn find_route(&self, start: &str, dest: &str) -> Option<Vec<EdgeEnd>> {
+        self.find_route_via(start, &[String::from(dest)])
+    }
+
+    // ==============================================================
+    // find_route_via
+    // ==============================================================
+    // Like find_route, but the route must pass through each of the
+    // given segments in order, the last of them being the
+    // destination. The search is over positions paired with the
+    // number of those segments reached so far, so a route may pass
+    // the same place more than once on its way between them. Among
+    // routes of equal cost the first found is kept.
+    pub fn find_route_via(&self, start: &str, via: &[String]) -> Option<Vec<EdgeEnd>> {
+        self.plan_routes(&start_positions(start), via, "", 1).pop()
+    }
+
+    // ==============================================================
+    // find_alternative_routes
+    // ==============================================================
+    // Returns up to `k` routes like find_route_via, cheapest first.
+    // None of them comes back to the same place heading the same way
+    // on the same leg of the route, so they differ by more than a
+    // detour round a loop.
+    pub fn find_alternative_routes(&self, start: &str, via: &[String], k: usize)
+        -> Vec<Vec<EdgeEnd>> {
+        self.plan_routes(&start_positions(start), via, "", k)
+    }
+
+    // The search behind find_route_via and find_alternative_routes,
+    // from any of the given starting positions. Each position on each
+    // leg of the route is settled at most `k` times, by the cheapest
+    // partial routes that reach it. Segments the named train is on
+    // itself do not count as occupied.
+    fn plan_routes(&self, starts: &[EdgeEnd], via: &[String], train: &str, k: usize)
+        -> Vec<Vec<EdgeEnd>> {
+        let mut routes = vec![];
+        if via.is_empty() || k == 0
+            || starts.iter().any(|s| !self.edge_map.contains_key(&s.ee_edge))
+            || via.iter().any(|v| !self.edge_map.contains_key(v)) {
+            return routes;
+        }
+        // Each label is a partial route: where it has got to, and the
+        // label of the partial route it extends.
+        let mut labels: Vec<(Key, Option<usize>)> = vec![];
+        let mut settled: HashMap<Key, usize> = HashMap::new();
+        // Ordered by cost, then by when the label was made.
+        let mut queue = BinaryHeap::new();
+        for start in starts {
+            let reached = if via[0] == start.ee_edge { 1 } else { 0 };
+            labels.push(((start.ee_edge.clone(), start.ee_end, reached), None));
+            queue.push(Reverse((0u32, labels.len() - 1)));
+        }
+
+        while let Some(Reverse((label_cost, ix))) = queue.pop() {
+            let key = labels[ix].0.clone();
+            let count = settled.entry(key.clone()).or_insert(0);
+            if *count >= k { continue; }
+            *count += 1;
+            if key.2 == via.len() {
+                // Walk back through the labels to recover the route.
+                let mut route = vec![];
+                let mut at = Some(ix);
+                while let Some(i) = at {
+                    let (edge, end, _) = &labels[i].0;
+                    route.push(EdgeEnd { ee_edge: edge.clone(), ee_end: *end });
+                    at = labels[i].1;
+                }
+                route.reverse();
+                routes.push(route);
+                if routes.len() == k { break; }
+                continue;
+            }
+            let pos = EdgeEnd { ee_edge: key.0.clone(), ee_end: key.1 };
+            let mut moves = self.get_next_moves(&pos);
+            if self.can_reverse(&pos) {
+                moves.push(EdgeEnd { ee_edge: pos.ee_edge.clone(),
+                                     ee_end: opposite_end(pos.ee_end) });
+            }
+            for next in moves {
+                if !self.is_move_open(&pos, &next) { continue; }
+                let step_cost = self.move_cost(&pos, &next, train).total(&self.cost_weights);
+                let reached = if next.ee_edge == via[key.2] { key.2 + 1 } else { key.2 };
+                let next_key = (next.ee_edge, next.ee_end, reached);
+                if settled.get(&next_key).is_some_and(|c| *c >= k) { continue; }
+                if k > 1 && label_visits(&labels, ix, &next_key) { continue; }
+                labels.push((next_key, Some(ix)));
+                queue.push(Reverse((label_cost.saturating_add(step_cost), labels.len() - 1)));
+            }
+        }
+        routes
+    }
+
+    // Returns false if maintenance or a fault rules out a move from
+    // `pos` to `next`: the next segment is closed, or the move is
+    // through a clamped junction that does not lead there or a stuck
+    // switch.
+    fn is_move_open(&self, pos: &EdgeEnd, next: &EdgeEnd) -> bool {
+        if next.ee_edge == pos.ee_edge { return true; }
+        match self.edge_map.get(&next.ee_edge) {
+            Some(e) if !e.closed => (),
+            _ => return false,
+        }
+        let ns = match self.edge_map.get(&pos.ee_edge) {
+            None => return false,
+            Some(e) => e.get_node(pos.ee_end),
+        };
+        match self.node_map.get(&ns.ns_node) {
+            Some(n) if n.clamped || self.is_switch_stuck(&n.name) => {
+                n.get_next(ns.ns_slot).ee_edge == next.ee_edge
+            }
+            _ => true,
+        }
+    }
+
+    // Returns what a move from `pos` to `next`, which is either a
+    // reversal or a move onto the next segment, adds to the cost of
+    // a route for the named train.
+    fn move_cost(&self, pos: &EdgeEnd, next: &EdgeEnd, train: &str) -> RouteCost {
+        let mut cost = RouteCost::default();
+        if next.ee_edge == pos.ee_edge {
+            cost.reversals = 1;
+            return cost;
+        }
+        let next_edge = match self.edge_map.get(&next.ee_edge) {
+            None => return cost,
+            Some(e) => e,
+        };
+        cost.length = next_edge.length;
+        if self.is_detected_occupied(&next_edge.name)
+            && (next_edge.train.is_empty() || next_edge.train != train) {
+            cost.occupied = 1;
+        }
+        let ns = match self.edge_map.get(&pos.ee_edge) {
+            None => return cost,
+            Some(e) => e.get_node(pos.ee_end),
+        };
+        if let Some(n) = self.node_map.get(&ns.ns_node) {
+            if n.get_node_type() == NodeType::Junction {
+                // The fork is the slot at the junction that is not the
+                // common one, whichever way the train passes through.
+                let fork = if ns.ns_slot == SLOT_1 {
+                    next_edge.get_node(opposite_end(next.ee_end)).ns_slot
+                } else {
+                    ns.ns_slot
+                };
+                if fork == SLOT_3 { cost.diverging = 1; } else { cost.straight = 1; }
+            }
+        }
+        cost
+    }
+
+    // ==============================================================
+    // route_cost
+    // ==============================================================
+    // Returns the breakdown of the cost of a route for the named
+    // train, the first entry of which is where the train starts.
+    pub fn route_cost(&self, route: &[EdgeEnd], train: &str) -> RouteCost {
+        let mut cost = RouteCost::default();
+        for pair in route.windows(2) {
+            cost.add(&self.move_cost(&pair[0], &pair[1], train));
+        }
+        cost
+    }
+
+    // Returns the breakdown of the cost of the rest of the named
+    // train's route, from where it is now.
+    pub fn train_route_cost(&self, name: &str) -> Option<RouteCost> {
+        let train = self.train_map.get(name)?;
+        let mut route = vec![train.edge.clone()];
+        route.extend(train.route.iter().cloned());
+        Some(self.route_cost(&route, name))
+    }
+
+    pub fn get_cost_weights(&self) -> &CostWeights {
+        &self.cost_weights
+    }
+
+    pub fn set_cost_weights(&mut self, weights: CostWeights) {
+        self.cost_weights = weights;
+    }
+
+    // The ticks a train waits to move on before the simulator looks
+    // for another route for it. Zero means it waits for as long as it
+    // takes.
+    pub fn get_reroute_wait(&self) -> u32 {
+        self.reroute_wait
+    }
+
+    pub fn set_reroute_wait(&mut self, ticks: u32) {
+        self.reroute_wait = ticks;
+    }
+
+    // ==============================================================
+    // reroute_train
+    // ==============================================================
+    // Gives the named train, which is held where it is, the cheapest
+    // of up to ALTERNATIVE_ROUTES routes through its remaining calls
+    // whose first move differs from the one it is held for and can be
+    // made now, signal and all, going on in the direction it is
+    // heading or reversing where that is allowed. Returns 16 if none
+    // of them can be taken, leaving the train as it was.
+    pub fn reroute_train(&mut self, name: &str) -> i32 {
+        let pos;
+        let calls;
+        let current;
+        match self.train_map.get(name) {
+            None => return 2, // ENOENT
+            Some(t) => {
+                pos = t.edge.clone();
+                calls = t.remaining_calls();
+                current = t.route.first().cloned();
+            }
+        }
+        let routes = self.plan_routes(std::slice::from_ref(&pos), &calls, name, ALTERNATIVE_ROUTES);
+        for mut route in routes {
+            if route.len() < 2 || current.as_ref() == Some(&route[1]) { continue; }
+            let reverses = route[1].ee_edge == pos.ee_edge;
+            if !self.can_move_to(&pos, &route[1]) || (!reverses && !self.is_signal_clear(&pos)) {
+                continue;
+            }
+            route.remove(0);
+            if let Some(t) = self.train_map.get_mut(name) {
+                t.set_route(route);
+                t.wait_ticks = 0;
+            }
+            self.log_event(Event::Rerouted { train: name.to_string(), segment: pos.ee_edge });
+            return 0;
+        }
+        16 // EBUSY
+    }
+
+    // ==============================================================
+    // get_optimal_route
+    // ==============================================================
+    // Plans the cheapest route of the named train from its current
+    // segment through its remaining waypoints to its destination, and
+    // sets the train's direction of travel to match. Returns non-zero
+    // if there is no such route.
+    pub fn get_optimal_route(&mut self, train: &str) -> i32 {
+        let calls = match self.train_map.get(train) {
+            None => return 1, // EINVAL
+            Some(t) => t.remaining_calls(),
+        };
+        self.route_train_via(train, &calls)
+    }
+
+    // ==============================================================
+    // route_train_via
+    // ==============================================================
+    // Plans the route of the named train from its current segment
+    // through each of the given segments in order, and sets the
+    // train's direction of travel to match. Returns non-zero if there
+    // is no such route.
+    pub fn route_train_via(&mut self, train: &str, via: &[String]) -> i32 {
+        let start = match self.train_map.get(train) {
+            None => return 1, // EINVAL
+            Some(t) => t.edge.ee_edge.clone(),
+        };
+        match self.plan_routes(&start_positions(&start), via, train, 1).pop() {
+            None => 1, // ENOENT
+            Some(mut route) => {
+                let first = route.remove(0);
+                if let Some(t) = self.train_map.get_mut(train) {
+                    t.edge.ee_end = first.ee_end;
+                    t.set_route(route);
+                }
+                0
+            }
+        }
+    }
+}
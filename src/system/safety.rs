@@ -0,0 +1,245 @@
+// safety.rs
+//
+// The safety invariant checker. After every simulation step the
+// state of the railroad is checked against the rules the signals and
+// switches exist to enforce:
+//
+//   - no two trains are on the same segment;
+//   - no train moved past a red signal without authority;
+//   - no train moved across a junction onto a leg the switch was not
+//     set for;
+//   - no train came off the track;
+//   - no junction was thrown while a train was on it.
+//
+// A train is taken to be on a junction from the step it crosses it
+// until it crosses the next node or reverses, since its tail may
+// still stand over the points. The moves, derailments and switch
+// throws made since the last check are recorded as they happen, so they can be
+// judged on the state at that moment. Any violation halts the
+// simulation: no further step is taken until the network is reset,
+// and the violations are kept for the report.
+//
+
+use super::common;
+use common::*;
+
+use super::blocks::BlockIndex;
+use super::System;
+
+use itertools::Itertools;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Invariant {
+    SharedSegment,
+    PassedRed,
+    WrongLeg,
+    Derailed,
+    ThrownOccupied,
+}
+
+impl Invariant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Invariant::SharedSegment => "shared-segment",
+            Invariant::PassedRed => "passed-red",
+            Invariant::WrongLeg => "wrong-leg",
+            Invariant::Derailed => "derailed",
+            Invariant::ThrownOccupied => "thrown-occupied",
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Violation {
+    pub tick: u32,
+    pub invariant: Invariant,
+    pub detail: String,
+}
+
+// A train move as it was made: where from and to, whether the signal
+// it passed was red without authority, where the node it crossed led
+// and, at a junction, the fork its switch was set for.
+pub(super) struct MoveRecord {
+    train: String,
+    from: EdgeEnd,
+    to: EdgeEnd,
+    node: String,
+    passed_red: bool,
+    led_to: String,
+    set_for: String,
+}
+
+// A train come off the track at a junction set against it.
+struct DerailRecord {
+    train: String,
+    segment: String,
+    node: String,
+    set_for: String,
+}
+
+// A junction thrown, with the trains that were on it at the time.
+struct ThrowRecord {
+    node: String,
+    occupants: Vec<String>,
+}
+
+#[derive(Default)]
+pub(super) struct SafetyLog {
+    moves: Vec<MoveRecord>,
+    derailments: Vec<DerailRecord>,
+    throws: Vec<ThrowRecord>,
+    violations: Vec<Violation>,
+}
+
+impl SafetyLog {
+    pub(super) fn clear(&mut self) {
+        self.moves.clear();
+        self.derailments.clear();
+        self.throws.clear();
+        self.violations.clear();
+    }
+}
+
+impl System {
+    // ==============================================================
+    // check_safety
+    // ==============================================================
+    // Checks the invariants against the current state and the moves,
+    // derailments and throws made since the last check, and returns
+    // the number of violations found. It is run at the end of every
+    // step, and any violation halts the simulation.
+    pub fn check_safety(&mut self) -> usize {
+        let mut found = vec![];
+        let tick = self.tick;
+        let mut violation = |invariant, detail| found.push(Violation { tick, invariant, detail });
+
+        let mut on_segment: Vec<(&String, &String)> = self.train_map.values()
+            .filter(|t| !t.edge.ee_edge.is_empty())
+            .map(|t| (&t.edge.ee_edge, &t.name))
+            .collect();
+        on_segment.sort();
+        for (segment, group) in &on_segment.iter().chunk_by(|(s, _)| *s) {
+            let trains: Vec<&str> = group.map(|(_, t)| t.as_str()).collect();
+            if trains.len() > 1 {
+                violation(Invariant::SharedSegment,
+                          format!("{} share {segment}", trains.join(", ")));
+            }
+        }
+
+        for m in &self.safety.moves {
+            let end = if m.from.ee_end == END_A { "A" } else { "B" };
+            if m.passed_red {
+                violation(Invariant::PassedRed,
+                          format!("{} passed the red signal at {} end {end} into {}",
+                                  m.train, m.from.ee_edge, m.to.ee_edge));
+            }
+            if m.led_to != m.to.ee_edge {
+                let set_for = if m.set_for.is_empty() { "nowhere" } else { &m.set_for };
+                violation(Invariant::WrongLeg,
+                          format!("{} crossed {} from {} into {}, but it was set for {set_for}",
+                                  m.train, m.node, m.from.ee_edge, m.to.ee_edge));
+            }
+        }
+
+        for d in &self.safety.derailments {
+            violation(Invariant::Derailed,
+                      format!("{} came off the track at {} from {} with the switch set for {}",
+                              d.train, d.node, d.segment, d.set_for));
+        }
+
+        for t in &self.safety.throws {
+            if !t.occupants.is_empty() {
+                violation(Invariant::ThrownOccupied,
+                          format!("{} was thrown with {} on it", t.node, t.occupants.join(", ")));
+            }
+        }
+
+        self.safety.moves.clear();
+        self.safety.derailments.clear();
+        self.safety.throws.clear();
+        let count = found.len();
+        self.safety.violations.extend(found);
+        count
+    }
+
+    // Returns true once a safety invariant has been violated.
+    pub fn is_halted(&self) -> bool {
+        !self.safety.violations.is_empty()
+    }
+
+    pub fn get_violations(&self) -> &Vec<Violation> {
+        &self.safety.violations
+    }
+
+    // Returns the trains standing on a junction: those that crossed
+    // it onto the segment they are on and have not since moved on or
+    // reversed.
+    pub fn junction_occupants(&self, node: &str) -> Vec<String> {
+        self.train_map.values()
+            .filter(|t| t.last_node == node)
+            .map(|t| t.name.clone())
+            .sorted()
+            .collect()
+    }
+
+    // Judges the move a train is about to make to the next segment on
+    // its route against the network as it stands: the signal is red if
+    // it shows red, is dark or would show red on the trains and
+    // switches now, whatever aspect it last showed. A reversal crosses
+    // no signal or node and is not judged.
+    pub(super) fn judge_move(&self, name: &str, authorised: bool, blocks: &BlockIndex) -> Option<MoveRecord> {
+        let train = self.train_map.get(name)?;
+        let (from, to) = match train.route.first() {
+            Some(next) if next.ee_edge != train.edge.ee_edge => (train.edge.clone(), next.clone()),
+            _ => return None,
+        };
+        let edge = self.edge_map.get(&from.ee_edge)?;
+        let passed_red = !authorised && edge.has_signal(from.ee_end)
+            && (edge.signals[from.ee_end].signal_is_red() || self.is_lamp_out(&from.ee_edge, from.ee_end)
+                || self.is_path_blocked(edge, from.ee_end, |s| self.is_block_occupied(blocks, s)));
+        let ns = edge.get_node(from.ee_end);
+        let (led_to, set_for) = match self.node_map.get(&ns.ns_node) {
+            Some(n) => {
+                let led_to = n.get_next_through(ns.ns_slot).ee_edge;
+                let set_for = if n.get_node_type() == NodeType::Junction { n.get_next(SLOT_1).ee_edge }
+                    else { led_to.clone() };
+                (led_to, set_for)
+            }
+            None => (String::new(), String::new()),
+        };
+        Some(MoveRecord { train: name.to_string(), from, to, node: ns.ns_node, passed_red, led_to, set_for })
+    }
+
+    // Records a move judged as it was made, for the next check.
+    pub(super) fn record_move(&mut self, record: Option<MoveRecord>) {
+        self.safety.moves.extend(record);
+    }
+
+    // Records a train come off the track at a junction from the
+    // segment it stands on.
+    pub(super) fn record_derailment(&mut self, name: &str, node: &str) {
+        let Some(train) = self.train_map.get(name) else { return };
+        let set_for = self.node_map.get(node).map(|n| n.get_next(SLOT_1).ee_edge).unwrap_or_default();
+        self.safety.derailments.push(DerailRecord {
+            train: name.to_string(), segment: train.edge.ee_edge.clone(), node: node.to_string(), set_for,
+        });
+    }
+
+    // Records a junction about to be thrown, with the trains on it.
+    pub(super) fn record_throw(&mut self, node: &str) {
+        let occupants = self.junction_occupants(node);
+        self.safety.throws.push(ThrowRecord { node: node.to_string(), occupants });
+    }
+
+    // Formats the violations found, one per line with its tick.
+    pub fn format_safety_report(&self) -> String {
+        if self.safety.violations.is_empty() {
+            return String::from("No safety violations\n");
+        }
+        let mut msg = String::from("SAFETY VIOLATION: the simulation has been halted\n");
+        for v in &self.safety.violations {
+            msg += &format!("  tick {:<6}{:<18}{}\n", v.tick, v.invariant.name(), v.detail);
+        }
+        msg
+    }
+}
@@ -0,0 +1,445 @@
+// simulation.rs
+//
+// Train motion simulation. Each step moves every train that is
+// able to do so from its current segment onto the next segment
+// of its route, then re-evaluates the signals for the new train
+// positions. The simulation is complete when no train can move.
+//
+
+use super::common;
+use common::*;
+
+use super::events::Event;
+use super::blocks::BlockIndex;
+use super::System;
+
+use itertools::Itertools;
+
+impl System {
+    // ==============================================================
+    // step
+    // ==============================================================
+    // Advances the simulation by one step, one tick of the clock, and
+    // returns the number of trains that moved. Trains due by the
+    // timetable are placed and switches in motion moved on first.
+    // Then each train in name order waits out its dwell at a
+    // waypoint, is rerouted if it has waited long enough, and moves or
+    // is held; reversing on a segment takes a step of its own. The
+    // moves, arrivals and trains held at red signals go in the event
+    // log with the tick, and the signals and safety are checked last.
+    pub fn step(&mut self) -> usize {
+        if self.is_halted() { return 0; }
+        self.dispatch_services();
+        self.tick += 1;
+        self.advance_switches();
+        let mut moved = 0;
+        let names: Vec<String> = self.train_map.keys().sorted().cloned().collect();
+        let blocks = self.index_blocks();
+        for name in names {
+            if self.wait_at_waypoint(&name) { continue; }
+            if self.is_reroute_due(&name) { self.reroute_train(&name); }
+            self.set_switch_ahead(&name);
+            if self.try_move_train(&name, &blocks) { moved += 1; continue; }
+            if let Some(t) = self.train_map.get_mut(&name) {
+                if !t.has_arrived() && !t.route.is_empty() { t.wait_ticks += 1; }
+            }
+            if let Some(signal) = self.held_at_signal(&name) {
+                self.log_event(Event::HeldAtSignal {
+                    train: name, segment: signal.ee_edge, end: signal.ee_end,
+                });
+            }
+        }
+        self.update_all_signals();
+        self.check_safety();
+        moved
+    }
+
+    // ==============================================================
+    // run
+    // ==============================================================
+    // Steps the simulation until no train can move any further, no
+    // train is stopped at a waypoint or waiting to be rerouted, and no
+    // train is still to depart by the timetable, and returns the
+    // number of steps in which at least one train moved. A service
+    // whose entry segment is still taken then can never depart, and
+    // is cancelled.
+    pub fn run(&mut self) -> u32 {
+        let mut steps = 0;
+        loop {
+            if self.is_halted() { break; }
+            let dwelling = self.has_dwelling_trains();
+            if self.step() > 0 { steps += 1; }
+            else if !dwelling && !self.has_pending_reroutes() && !self.has_pending_departures()
+                    && !self.has_moving_switches() {
+                self.cancel_stranded_services();
+                break;
+            }
+        }
+        steps
+    }
+
+    // Whether the simulator sets the switch of each junction a train
+    // comes to the way its route goes. Otherwise switches are only
+    // thrown by hand.
+    pub fn get_auto_switching(&self) -> bool {
+        self.auto_switching
+    }
+
+    pub fn set_auto_switching(&mut self, on: bool) {
+        self.auto_switching = on;
+    }
+
+    // With automatic switching on, throws the switch of the junction
+    // ahead of a train the way its route goes, before the train moves,
+    // if it is set the other way and the junction is free:
+    // not clamped, stuck or already moving, with no train standing on
+    // it and no other train waiting at it for the way it is set now.
+    // Returns true if the switch was thrown.
+    fn set_switch_ahead(&mut self, name: &str) -> bool {
+        if !self.auto_switching { return false; }
+        let node = match self.switch_wanted(name) {
+            None => return false,
+            Some(n) => n,
+        };
+        match self.node_map.get(&node) {
+            None => return false,
+            Some(n) => if n.clamped || n.is_in_motion() { return false; }
+        }
+        if self.is_switch_stuck(&node)
+            || !self.junction_occupants(&node).is_empty()
+            || self.is_switch_in_use(&node, name) {
+            return false;
+        }
+        self.toggle_switch(&node) == 0
+    }
+
+    // Returns the junction at the end of a train's segment if its
+    // switch is not set for the train's next move.
+    fn switch_wanted(&self, name: &str) -> Option<String> {
+        let train = self.train_map.get(name)?;
+        if train.has_arrived() || train.dwell_remaining > 0 { return None; }
+        let next = train.route.first()?;
+        if next.ee_edge == train.edge.ee_edge { return None; }
+        let ns = self.edge_map.get(&train.edge.ee_edge)?.get_node(train.edge.ee_end);
+        let node = self.node_map.get(&ns.ns_node)?;
+        if node.get_node_type() != NodeType::Junction { return None; }
+        if node.get_next_through(ns.ns_slot).ee_edge == next.ee_edge { return None; }
+        Some(ns.ns_node)
+    }
+
+    // Returns true if a train other than `except` is waiting at the
+    // junction to make a move its switch is set for now.
+    fn is_switch_in_use(&self, node: &str, except: &str) -> bool {
+        let junction = match self.node_map.get(node) {
+            None => return false,
+            Some(n) => n,
+        };
+        self.train_map.values().any(|t| {
+            if t.name == except || t.has_arrived() { return false; }
+            let next = match t.route.first() {
+                Some(next) if next.ee_edge != t.edge.ee_edge => next,
+                _ => return false,
+            };
+            match self.edge_map.get(&t.edge.ee_edge) {
+                None => false,
+                Some(e) => {
+                    let ns = e.get_node(t.edge.ee_end);
+                    ns.ns_node == node && junction.get_next(ns.ns_slot).ee_edge == next.ee_edge
+                }
+            }
+        })
+    }
+
+    pub fn has_moving_switches(&self) -> bool {
+        self.node_map.values().any(|n| n.is_in_motion())
+    }
+
+    // Moves each switch in motion on by a tick. Those that come to
+    // rest are logged, and the signals updated for their new position
+    // before any train moves.
+    fn advance_switches(&mut self) {
+        let mut settled = vec![];
+        for name in self.node_map.keys().sorted().cloned().collect::<Vec<String>>() {
+            if let Some(n) = self.node_map.get_mut(&name) {
+                if n.advance_throw() { settled.push((name, n.get_switch_pos())); }
+            }
+        }
+        if settled.is_empty() { return; }
+        for (node, position) in settled {
+            self.log_event(Event::SwitchSettled { node, position });
+        }
+        self.update_all_signals();
+    }
+
+    // Returns true while some train is waiting to move on and has not
+    // yet waited long enough to be rerouted.
+    pub fn has_pending_reroutes(&self) -> bool {
+        self.reroute_wait > 0
+            && self.train_map.values().any(|t| t.wait_ticks > 0 && t.wait_ticks <= self.reroute_wait)
+    }
+
+    fn is_reroute_due(&self, name: &str) -> bool {
+        match self.train_map.get(name) {
+            Some(t) => self.reroute_wait > 0 && t.wait_ticks >= self.reroute_wait,
+            None => false,
+        }
+    }
+
+    // Returns true while some train is stopped at a waypoint.
+    pub fn has_dwelling_trains(&self) -> bool {
+        self.train_map.values().any(|t| t.dwell_remaining > 0)
+    }
+
+    // Counts down the dwell of a train stopped at a waypoint. Returns
+    // true if the train spends this step waiting there.
+    fn wait_at_waypoint(&mut self, name: &str) -> bool {
+        match self.train_map.get_mut(name) {
+            Some(t) if t.dwell_remaining > 0 => {
+                t.dwell_remaining -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Returns true if the train may move onto the next segment of
+    // its route: it must not be stopped at a waypoint, the junction at
+    // the end of its current segment must lead there, the signal (if
+    // any) must be green, and the next segment must be clear. If the
+    // next step is to reverse, it must be allowed where the train is.
+    // Trailing through a junction past a signal, the train goes on the
+    // aspect, which was cleared with the switch set for its fork;
+    // facing the points, it waits until they lead its way.
+    pub fn can_train_move(&self, name: &str) -> bool {
+        let train = match self.train_map.get(name) {
+            None => return false,
+            Some(t) => t,
+        };
+        if train.has_arrived() || train.dwell_remaining > 0 || train.derailed_at.is_some() {
+            return false;
+        }
+        let next = match train.route.first() {
+            None => return false,
+            Some(n) => n,
+        };
+        if next.ee_edge == train.edge.ee_edge {
+            return self.can_reverse(&train.edge);
+        }
+        if !self.is_signal_clear(&train.edge) { return false; }
+        if self.is_signalled_trailing(&train.edge) {
+            return self.is_segment_free(&next.ee_edge);
+        }
+        self.can_move_to(&train.edge, next)
+    }
+
+    // Returns true if a train at `pos` is at a signal on a fork of the
+    // junction ahead.
+    fn is_signalled_trailing(&self, pos: &EdgeEnd) -> bool {
+        let Some(edge) = self.edge_map.get(&pos.ee_edge) else { return false };
+        let ns = edge.get_node(pos.ee_end);
+        edge.has_signal(pos.ee_end) && ns.ns_slot != SLOT_1
+            && self.node_map.get(&ns.ns_node).is_some_and(|n| n.get_node_type() == NodeType::Junction)
+    }
+
+    // Returns true if there is no signal at the end of the segment a
+    // train at `pos` is heading for, or it is not red.
+    pub(super) fn is_signal_clear(&self, pos: &EdgeEnd) -> bool {
+        match self.edge_map.get(&pos.ee_edge) {
+            None => false,
+            Some(e) => {
+                let signal = &e.signals[pos.ee_end];
+                signal.edge.ee_edge.is_empty() || !signal.signal_is_red()
+            }
+        }
+    }
+
+    // Returns true if a train at `pos` could make the move to `next`
+    // now, signals aside: a reversal must be allowed where it is, and
+    // otherwise the junction at the end of its segment must lead to
+    // the next segment, which must be clear and in service.
+    pub(super) fn can_move_to(&self, pos: &EdgeEnd, next: &EdgeEnd) -> bool {
+        if next.ee_edge == pos.ee_edge {
+            return self.can_reverse(pos);
+        }
+        let edge = match self.edge_map.get(&pos.ee_edge) {
+            None => return false,
+            Some(e) => e,
+        };
+        let ns = edge.get_node(pos.ee_end);
+        match self.node_map.get(&ns.ns_node) {
+            None => return false,
+            Some(n) => {
+                if n.get_node_type() == NodeType::Empty { return false; }
+                let ahead = n.get_next_through(ns.ns_slot);
+                if ahead.ee_edge != next.ee_edge
+                    || ahead.ee_end != opposite_end(next.ee_end) {
+                    return false;
+                }
+            }
+        }
+        self.is_segment_free(&next.ee_edge)
+    }
+
+    // Returns true if a segment is in service and its detector reports
+    // no train on it.
+    fn is_segment_free(&self, segment: &str) -> bool {
+        match self.edge_map.get(segment) {
+            None => false,
+            Some(e) => !e.closed && !self.is_detected_occupied(&e.name),
+        }
+    }
+
+    // Returns the segment end of the red signal holding the train, if
+    // the train is waiting at one on its way to its destination.
+    fn held_at_signal(&self, name: &str) -> Option<EdgeEnd> {
+        let train = self.train_map.get(name)?;
+        if train.has_arrived() { return None; }
+        // A train about to reverse is not waiting for this signal.
+        if train.route.first()?.ee_edge == train.edge.ee_edge { return None; }
+        let edge = self.edge_map.get(&train.edge.ee_edge)?;
+        let signal = &edge.signals[train.edge.ee_end];
+        if signal.edge.ee_edge.is_empty() || !signal.signal_is_red() { return None; }
+        Some(train.edge.clone())
+    }
+
+    // The move is judged for safety as the network stands before the
+    // train's own checks, so that the safety check does not merely
+    // repeat them, and recorded if the train goes.
+    fn try_move_train(&mut self, name: &str, blocks: &BlockIndex) -> bool {
+        let record = self.judge_move(name, false, blocks);
+        if !self.can_train_move(name) { return false; }
+        self.record_move(record);
+        self.move_train(name)
+    }
+
+    // ==============================================================
+    // force_train
+    // ==============================================================
+    // Authorises a train past the signal at the end of its segment,
+    // red or dark, to make the next move on its route. A train forced
+    // through a trailable junction set against it runs through; one
+    // forced through a junction set against it that cannot be run
+    // through comes off the track there, and the derailment halts the
+    // simulation. The move is checked for safety at once. Returns 2 if
+    // there is no such train, 16 if the next segment is occupied or
+    // closed, and 22 if the train has no move to make on its own
+    // authority, or a facing switch would send it off its route.
+    pub fn force_train(&mut self, name: &str) -> i32 {
+        let (pos, next) = match self.train_map.get(name) {
+            None => return 2, // ENOENT
+            Some(t) => {
+                if t.has_arrived() || t.dwell_remaining > 0 || t.derailed_at.is_some() {
+                    return 22; // EINVAL
+                }
+                match t.route.first() {
+                    Some(next) if next.ee_edge != t.edge.ee_edge => (t.edge.clone(), next.clone()),
+                    _ => return 22, // EINVAL
+                }
+            }
+        };
+        match self.edge_map.get(&next.ee_edge) {
+            Some(e) if e.train.is_empty() && !e.closed => (),
+            _ => return 16, // EBUSY
+        }
+        let ns = match self.edge_map.get(&pos.ee_edge) {
+            None => return 2, // ENOENT
+            Some(e) => e.get_node(pos.ee_end),
+        };
+        let derails;
+        match self.node_map.get(&ns.ns_node) {
+            None => return 2, // ENOENT
+            Some(n) => {
+                if n.get_node_type() == NodeType::Empty { return 22; } // EINVAL
+                let ahead = n.get_next_through(ns.ns_slot);
+                if ahead.ee_edge == next.ee_edge { derails = false; }
+                else if ns.ns_slot == SLOT_1 || n.get_node_type() != NodeType::Junction {
+                    return 22; // EINVAL
+                }
+                else { derails = true; }
+            }
+        }
+        if derails {
+            self.record_derailment(name, &ns.ns_node);
+            if let Some(t) = self.train_map.get_mut(name) {
+                t.derailed_at = Some(ns.ns_node.clone());
+            }
+            self.log_event(Event::Derailed { train: name.to_string(), node: ns.ns_node });
+        }
+        else {
+            let record = self.judge_move(name, true, &self.index_blocks());
+            self.record_move(record);
+            self.move_train(name);
+        }
+        self.update_all_signals();
+        self.check_safety();
+        0
+    }
+
+    // Moves a train on to the next segment of its route, or reverses
+    // it. A train trailing through a trailable junction set against
+    // it forces the switch over on the way.
+    fn move_train(&mut self, name: &str) -> bool {
+        self.trail_switch(name);
+        let crossed = self.train_map.get(name)
+            .and_then(|t| self.edge_map.get(&t.edge.ee_edge).map(|e| e.get_node(t.edge.ee_end).ns_node))
+            .unwrap_or_default();
+
+        let from;
+        let next;
+        let arrived;
+        match self.train_map.get_mut(name) {
+            None => return false,
+            Some(t) => {
+                from = t.edge.ee_edge.clone();
+                next = t.route.remove(0);
+                t.edge = next.clone();
+                t.wait_ticks = 0;
+                if from == next.ee_edge {
+                    t.last_node.clear();
+                    self.log_event(Event::Reversed { train: name.to_string(), segment: from });
+                    return true;
+                }
+                t.last_node = crossed;
+                t.reach_waypoint();
+                arrived = t.has_arrived();
+            }
+        }
+        if let Some(e) = self.edge_map.get_mut(&from) {
+            e.set_train("");
+        }
+        if let Some(e) = self.edge_map.get_mut(&next.ee_edge) {
+            e.set_train(name);
+        }
+        self.log_event(Event::EnteredSegment {
+            train: name.to_string(), segment: next.ee_edge.clone(),
+        });
+        if arrived {
+            self.log_event(Event::Arrived { train: name.to_string(), segment: next.ee_edge });
+        }
+        true
+    }
+
+    // Forces over the switch of a trailable junction that a train is
+    // about to run through from the fork it is not set for, rather
+    // than holding the train until the switch is thrown for it.
+    fn trail_switch(&mut self, name: &str) {
+        let (pos, next) = match self.train_map.get(name) {
+            Some(t) => match t.route.first() {
+                Some(next) if next.ee_edge != t.edge.ee_edge => (t.edge.clone(), next.clone()),
+                _ => return,
+            },
+            None => return,
+        };
+        let ns = match self.edge_map.get(&pos.ee_edge) {
+            None => return,
+            Some(e) => e.get_node(pos.ee_end),
+        };
+        match self.node_map.get_mut(&ns.ns_node) {
+            Some(n) if n.is_run_through(ns.ns_slot) && n.get_edge_end(SLOT_1).ee_edge == next.ee_edge => {
+                n.set_switch_pos(if ns.ns_slot == SLOT_2 { JSwitch::JSwitchLeft } else { JSwitch::JSwitchRight });
+            }
+            _ => return,
+        }
+        self.log_event(Event::Trailed { train: name.to_string(), node: ns.ns_node });
+    }
+}
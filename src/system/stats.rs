@@ -0,0 +1,155 @@
+// stats.rs
+//
+// Statistics computed from the simulation event log, to compare
+// signalling layouts: for each train how long its journey took, how
+// long it was held at red signals and how far it went; for each
+// segment the share of time a train was on it; and for each junction
+// how often its switch was thrown.
+//
+
+use super::events::Event;
+use super::System;
+
+use itertools::Itertools;
+use std::collections::HashMap;
+
+pub struct TrainStats {
+    pub name: String,
+    // Ticks from being placed to arriving, if the train has arrived.
+    pub travel_ticks: Option<u32>,
+    // Ticks spent waiting at a red signal.
+    pub held_ticks: u32,
+    // Segments entered after being placed, and their total length.
+    pub segments: u32,
+    pub distance: u32,
+}
+
+pub struct SegmentStats {
+    pub name: String,
+    // Ticks at whose end a train was on the segment.
+    pub occupied_ticks: u32,
+    // The same as a percentage of all ticks.
+    pub occupancy: f64,
+}
+
+pub struct JunctionStats {
+    pub name: String,
+    pub throws: u32,
+}
+
+pub struct Statistics {
+    pub ticks: u32,
+    pub trains: Vec<TrainStats>,
+    pub segments: Vec<SegmentStats>,
+    pub junctions: Vec<JunctionStats>,
+}
+
+impl Statistics {
+    // Formats the statistics as three tables: trains, segments and
+    // junctions.
+    pub fn format(&self) -> String {
+        let mut msg = format!("Simulation statistics after {} tick(s)\n\n", self.ticks);
+        msg += &format!("{:<12}{:>8}{:>8}{:>10}{:>10}\n",
+                        "Train", "Travel", "Held", "Segments", "Distance");
+        for t in &self.trains {
+            let travel = match t.travel_ticks {
+                Some(n) => n.to_string(),
+                None => String::from("-"),
+            };
+            msg += &format!("{:<12}{:>8}{:>8}{:>10}{:>10}\n",
+                            t.name, travel, t.held_ticks, t.segments, t.distance);
+        }
+        msg += &format!("\n{:<12}{:>10}\n", "Segment", "Occupied");
+        for s in &self.segments {
+            msg += &format!("{:<12}{:>9.1}%\n", s.name, s.occupancy);
+        }
+        msg += &format!("\n{:<12}{:>8}\n", "Junction", "Throws");
+        for j in &self.junctions {
+            msg += &format!("{:<12}{:>8}\n", j.name, j.throws);
+        }
+        msg
+    }
+}
+
+impl System {
+    // ==============================================================
+    // compute_statistics
+    // ==============================================================
+    // Works through the event log from the start of the simulation.
+    // Placing a train again starts a new journey, so its figures are
+    // for the latest journey only.
+    pub fn compute_statistics(&self) -> Statistics {
+        let mut trains: HashMap<String, TrainStats> = self.train_map.keys()
+            .map(|name| (name.clone(), TrainStats {
+                name: name.clone(), travel_ticks: None, held_ticks: 0, segments: 0, distance: 0,
+            }))
+            .collect();
+        let mut placed: HashMap<String, u32> = HashMap::new();
+        let mut position: HashMap<String, String> = HashMap::new();
+        let mut occupied: HashMap<String, u32> = HashMap::new();
+        let mut throws: HashMap<String, u32> = HashMap::new();
+
+        let mut events = self.events.iter().peekable();
+        for tick in 0..=self.tick {
+            while let Some(entry) = events.next_if(|e| e.tick <= tick) {
+                match &entry.event {
+                    Event::Placed { train, segment } => {
+                        placed.insert(train.clone(), entry.tick);
+                        position.insert(train.clone(), segment.clone());
+                        if let Some(t) = trains.get_mut(train) {
+                            t.travel_ticks = None;
+                            t.held_ticks = 0;
+                            t.segments = 0;
+                            t.distance = 0;
+                        }
+                    }
+                    Event::EnteredSegment { train, segment } => {
+                        position.insert(train.clone(), segment.clone());
+                        if let Some(t) = trains.get_mut(train) {
+                            t.segments += 1;
+                            t.distance += self.edge_map.get(segment).map_or(0, |e| e.length);
+                        }
+                    }
+                    Event::Cancelled { train, .. } => {
+                        position.remove(train);
+                    }
+                    Event::HeldAtSignal { train, .. } => {
+                        if let Some(t) = trains.get_mut(train) { t.held_ticks += 1; }
+                    }
+                    Event::Arrived { train, .. } => {
+                        if let Some(t) = trains.get_mut(train) {
+                            t.travel_ticks = Some(entry.tick - placed.get(train).copied().unwrap_or(0));
+                        }
+                    }
+                    Event::SwitchThrown { node, .. } => {
+                        *throws.entry(node.clone()).or_insert(0) += 1;
+                    }
+                    Event::SignalChanged { .. } | Event::SwitchSettled { .. } | Event::Reversed { .. }
+                    | Event::Rerouted { .. } | Event::Trailed { .. } | Event::Derailed { .. }
+                    | Event::FaultInjected { .. } | Event::FaultCleared { .. } => (),
+                }
+            }
+            // Tick 0 is the starting state, not a tick of running time.
+            if tick == 0 { continue; }
+            for segment in position.values().unique() {
+                *occupied.entry(segment.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let segments = self.get_all_edges().into_iter().map(|name| {
+            let ticks = occupied.get(&name).copied().unwrap_or(0);
+            let occupancy = if self.tick == 0 { 0.0 }
+                            else { 100.0 * ticks as f64 / self.tick as f64 };
+            SegmentStats { name, occupied_ticks: ticks, occupancy }
+        }).collect();
+        let junctions = self.get_all_junctions().into_iter().map(|name| {
+            let throws = throws.get(&name).copied().unwrap_or(0);
+            JunctionStats { name, throws }
+        }).collect();
+        let trains = trains.into_iter()
+            .sorted_by(|a, b| a.0.cmp(&b.0))
+            .map(|(_, t)| t)
+            .collect();
+        Statistics { ticks: self.tick, trains, segments, junctions }
+    }
+}
@@ -0,0 +1,269 @@
+// svg.rs
+//
+// Draws the track schematic and the state of the simulation as an
+// SVG image: track segments laid out by compute_layout, the leg each
+// junction switch is set to, signal heads colored by their aspect,
+// and the trains on the segments they occupy. A recorded run can be
+// drawn as a series of such pictures or as one animated picture that
+// plays them back a tick at a time.
+//
+
+use super::common;
+use common::*;
+
+use super::replay::{Replay, TickState};
+use super::System;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Pixels per unit of segment length and per row of the layout.
+const SCALE_X: f64 = 80.0;
+const SCALE_Y: f64 = 50.0;
+const MARGIN: f64 = 40.0;
+
+// Distance of a signal head along the track from its node, and out
+// to the side of the track.
+const SIGNAL_ALONG: f64 = 16.0;
+const SIGNAL_ASIDE: f64 = 10.0;
+
+// Fraction of a junction leg highlighted from the junction.
+const LEG_HIGHLIGHT: f64 = 0.45;
+
+const TRACK_COLOR: &str = "#555555";
+const LEG_COLOR: &str = "#1e90ff";
+const TRAIN_COLOR: &str = "#ff8c00";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Returns the unit vector from `a` toward `b`, or along X when the
+// two points are the same.
+fn direction(a: Point, b: Point) -> Point {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len == 0.0 { Point { x: 1.0, y: 0.0 } } else { Point { x: dx / len, y: dy / len } }
+}
+
+// Node positions in pixels, and the size of the picture.
+struct Canvas {
+    nodes: HashMap<String, Point>,
+    width: f64,
+    height: f64,
+}
+
+impl Canvas {
+    fn new(layout: HashMap<String, Point>) -> Canvas {
+        let min_x = layout.values().map(|p| p.x).fold(f64::INFINITY, f64::min);
+        let max_x = layout.values().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
+        let min_y = layout.values().map(|p| p.y).fold(f64::INFINITY, f64::min);
+        let max_y = layout.values().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max);
+        if layout.is_empty() {
+            return Canvas { nodes: layout, width: 2.0 * MARGIN, height: 2.0 * MARGIN };
+        }
+        let nodes = layout.into_iter().map(|(name, p)| {
+            (name, Point { x: MARGIN + (p.x - min_x) * SCALE_X,
+                           y: MARGIN + (p.y - min_y) * SCALE_Y })
+        }).collect();
+        Canvas {
+            nodes,
+            width: 2.0 * MARGIN + (max_x - min_x) * SCALE_X,
+            height: 2.0 * MARGIN + (max_y - min_y) * SCALE_Y,
+        }
+    }
+
+    fn node(&self, name: &String) -> Option<Point> {
+        self.nodes.get(name).copied()
+    }
+
+    fn open(&self) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                               width=\"{w:.0}\" height=\"{h:.0}\" \
+                               viewBox=\"0 0 {w:.0} {h:.0}\" \
+                               font-family=\"sans-serif\" font-size=\"10\">",
+                         w = self.width, h = self.height);
+        let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>");
+        svg
+    }
+}
+
+impl System {
+    // ==============================================================
+    // to_svg
+    // ==============================================================
+    // Returns an SVG picture of the network as it stands: every track
+    // segment, the active leg of each junction, each signal head in
+    // red or green at its end of the segment, and each train labelled
+    // on the segment it occupies.
+    pub fn to_svg(&self) -> String {
+        let canvas = Canvas::new(self.compute_layout());
+        canvas.open() + &self.draw_tracks(&canvas)
+            + &self.draw_state(&canvas, &self.capture_state(0)) + "</svg>\n"
+    }
+
+    // ==============================================================
+    // replay_frames
+    // ==============================================================
+    // Returns one SVG picture for each tick of a recorded run, each
+    // captioned with its tick.
+    pub fn replay_frames(&self, replay: &Replay) -> Vec<String> {
+        let canvas = Canvas::new(self.compute_layout());
+        let tracks = self.draw_tracks(&canvas);
+        replay.frames.iter().map(|state| {
+            canvas.open() + &tracks + &self.draw_state(&canvas, state)
+                + &caption(state) + "</svg>\n"
+        }).collect()
+    }
+
+    // ==============================================================
+    // replay_to_svg
+    // ==============================================================
+    // Returns an animated SVG picture that plays back a recorded run,
+    // showing each tick for `tick_secs` seconds and then starting
+    // over. The track is drawn once, with one layer per tick shown in
+    // turn above it.
+    pub fn replay_to_svg(&self, replay: &Replay, tick_secs: f64) -> String {
+        let canvas = Canvas::new(self.compute_layout());
+        let mut svg = canvas.open() + &self.draw_tracks(&canvas);
+        let count = replay.frames.len();
+        let total = tick_secs * count as f64;
+        for (ix, state) in replay.frames.iter().enumerate() {
+            // Discrete key times: hidden until this tick's slot comes
+            // round, visible for one slot, then hidden again.
+            let start = ix as f64 / count as f64;
+            let end = (ix + 1) as f64 / count as f64;
+            let (values, times) = match (ix == 0, ix + 1 == count) {
+                (true, true) => (String::from("visible"), String::from("0")),
+                (true, false) => (String::from("visible;hidden"), format!("0;{end:.6}")),
+                (false, true) => (String::from("hidden;visible"), format!("0;{start:.6}")),
+                (false, false) => (String::from("hidden;visible;hidden"),
+                                   format!("0;{start:.6};{end:.6}")),
+            };
+            let _ = writeln!(svg, "<g id=\"tick{}\" visibility=\"hidden\">", state.tick);
+            let _ = writeln!(svg, "<animate attributeName=\"visibility\" values=\"{values}\" \
+                                   keyTimes=\"{times}\" calcMode=\"discrete\" \
+                                   dur=\"{total:.3}s\" repeatCount=\"indefinite\"/>");
+            svg += &self.draw_state(&canvas, state);
+            svg += &caption(state);
+            svg += "</g>\n";
+        }
+        svg + "</svg>\n"
+    }
+
+    // Draws the parts of the picture that do not change as the
+    // simulation runs: the track segments with their names and the
+    // nodes.
+    fn draw_tracks(&self, canvas: &Canvas) -> String {
+        let mut svg = String::from("<g id=\"tracks\">\n");
+        for name in self.get_all_edges() {
+            let edge = &self.edge_map[&name];
+            let (a, b) = match (canvas.node(&edge.ends[END_A].ns_node),
+                                canvas.node(&edge.ends[END_B].ns_node)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let _ = writeln!(svg, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" \
+                                   stroke=\"{TRACK_COLOR}\" stroke-width=\"4\"/>",
+                             a.x, a.y, b.x, b.y);
+            let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" \
+                                   fill=\"{TRACK_COLOR}\">{}</text>",
+                             (a.x + b.x) / 2.0, (a.y + b.y) / 2.0 - 8.0, escape(&name));
+        }
+        for name in self.get_all_nodes() {
+            let p = match canvas.node(&name) { Some(p) => p, None => continue };
+            if self.node_map[&name].get_node_type() == NodeType::Junction {
+                let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"5\" fill=\"white\" \
+                                       stroke=\"{LEG_COLOR}\" stroke-width=\"2\">\
+                                       <title>{}</title></circle>", p.x, p.y, escape(&name));
+            }
+            else {
+                let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" \
+                                       fill=\"{TRACK_COLOR}\"/>", p.x, p.y);
+            }
+        }
+        svg + "</g>\n"
+    }
+
+    // Draws the parts of the picture given by the state of the
+    // simulation: junction legs, signal aspects and trains.
+    fn draw_state(&self, canvas: &Canvas, state: &TickState) -> String {
+        // The common leg of each junction and the leg its switch is
+        // set to, highlighted out from the junction.
+        let mut svg = String::from("<g class=\"switches\">\n");
+        for (name, pos) in &state.switches {
+            let (node, p) = match (self.node_map.get(name), canvas.node(name)) {
+                (Some(n), Some(p)) => (n, p),
+                _ => continue,
+            };
+            let active = match pos {
+                JSwitch::JSwitchLeft => Some(SLOT_2),
+                JSwitch::JSwitchRight => Some(SLOT_3),
+                JSwitch::JSwitchNone => None,
+            };
+            for slot in [Some(SLOT_1), active].into_iter().flatten() {
+                let ee = &node.slots[slot];
+                let far = match self.edge_map.get(&ee.ee_edge) {
+                    Some(e) => canvas.node(&e.get_adjacent(ee.ee_end).ns_node),
+                    None => None,
+                };
+                if let Some(q) = far {
+                    let _ = writeln!(svg, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" \
+                                           y2=\"{:.1}\" stroke=\"{LEG_COLOR}\" \
+                                           stroke-width=\"6\" stroke-linecap=\"round\"/>",
+                                     p.x, p.y,
+                                     p.x + (q.x - p.x) * LEG_HIGHLIGHT,
+                                     p.y + (q.y - p.y) * LEG_HIGHLIGHT);
+                }
+            }
+        }
+        svg += "</g>\n";
+
+        // Signal heads, just inside the end of the segment they guard
+        // and out to the side, on a short mast.
+        svg += "<g class=\"signals\">\n";
+        for (name, end, red) in &state.signals {
+            let edge = match self.edge_map.get(name) { Some(e) => e, None => continue };
+            let (p, q) = match (canvas.node(&edge.ends[*end].ns_node),
+                                canvas.node(&edge.ends[opposite_end(*end)].ns_node)) {
+                (Some(p), Some(q)) => (p, q),
+                _ => continue,
+            };
+            let u = direction(p, q);
+            let base = Point { x: p.x + u.x * SIGNAL_ALONG, y: p.y + u.y * SIGNAL_ALONG };
+            let head = Point { x: base.x - u.y * SIGNAL_ASIDE, y: base.y + u.x * SIGNAL_ASIDE };
+            let color = if *red { "red" } else { "limegreen" };
+            let _ = writeln!(svg, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" \
+                                   stroke=\"black\" stroke-width=\"1\"/>",
+                             base.x, base.y, head.x, head.y);
+            let _ = writeln!(svg, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"5\" fill=\"{color}\" \
+                                   stroke=\"black\"><title>{} end {}</title></circle>",
+                             head.x, head.y, escape(name), if *end == END_A { "A" } else { "B" });
+        }
+        svg += "</g>\n";
+
+        // Trains, on the middle of their segment.
+        svg += "<g class=\"trains\">\n";
+        for (train, name) in &state.trains {
+            let edge = match self.edge_map.get(name) { Some(e) => e, None => continue };
+            let (a, b) = match (canvas.node(&edge.ends[END_A].ns_node),
+                                canvas.node(&edge.ends[END_B].ns_node)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let mid = Point { x: (a.x + b.x) / 2.0, y: (a.y + b.y) / 2.0 };
+            let _ = writeln!(svg, "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"36\" height=\"12\" \
+                                   rx=\"3\" fill=\"{TRAIN_COLOR}\" stroke=\"black\"/>",
+                             mid.x - 18.0, mid.y - 6.0);
+            let _ = writeln!(svg, "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" \
+                                   font-weight=\"bold\">{}</text>",
+                             mid.x, mid.y + 20.0, escape(train));
+        }
+        svg + "</g>\n"
+    }
+}
+
+fn caption(state: &TickState) -> String {
+    format!("<text x=\"8\" y=\"16\" font-size=\"12\">Tick {}</text>\n", state.tick)
+}
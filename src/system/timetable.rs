@@ -0,0 +1,279 @@
+// timetable.rs
+//
+// Timetable driven dispatching. A timetable lists services: the train
+// to run, the segment it enters the network on, the tick it departs
+// and the segments it calls at, the last of which is its destination.
+// Any call may carry the tick it is scheduled for, and a stop short of
+// the destination the ticks the train dwells there. The simulator
+// places each train on its entry segment once its departure tick has
+// come and the segment is clear, routes it through its calls, and
+// the timetable report compares what happened against the schedule.
+//
+// A timetable file has one service per line:
+//
+//     service: name,entry,departure,call[@tick][+dwell],...,destination[@tick]
+//
+// Blank lines and lines starting with '#' are ignored.
+//
+
+use super::events::Event;
+use super::train::Waypoint;
+use super::System;
+
+use std::io::BufRead;
+
+pub struct Call {
+    pub segment: String,
+    pub time: Option<u32>,
+    pub dwell: u32,
+}
+
+pub struct Service {
+    pub train: String,
+    pub entry: String,
+    pub departure: u32,
+    // The stops in order, ending with the destination.
+    pub calls: Vec<Call>,
+    // Set once the service is dispatched and no route is found for
+    // it, so it is not dispatched again.
+    pub cancelled: bool,
+}
+
+pub struct CallReport {
+    // "depart", "stop" or "arrive".
+    pub kind: &'static str,
+    pub segment: String,
+    pub scheduled: Option<u32>,
+    pub actual: Option<u32>,
+}
+
+impl CallReport {
+    // Ticks behind schedule, negative if early, when both the
+    // scheduled and actual times are known.
+    pub fn lateness(&self) -> Option<i64> {
+        match (self.scheduled, self.actual) {
+            (Some(s), Some(a)) => Some(a as i64 - s as i64),
+            _ => None,
+        }
+    }
+}
+
+pub struct ServiceReport {
+    pub train: String,
+    pub calls: Vec<CallReport>,
+}
+
+fn parse_call(field: &str) -> Option<Call> {
+    let (field, dwell) = match field.split_once('+') {
+        Some((f, d)) => (f, d.trim().parse().ok()?),
+        None => (field, 0),
+    };
+    let (segment, time) = match field.split_once('@') {
+        Some((s, t)) => (s.trim(), Some(t.trim().parse().ok()?)),
+        None => (field.trim(), None),
+    };
+    if segment.is_empty() { return None; }
+    Some(Call { segment: String::from(segment), time, dwell })
+}
+
+fn format_tick(tick: Option<u32>) -> String {
+    tick.map_or(String::from("-"), |t| t.to_string())
+}
+
+impl System {
+    // ==============================================================
+    // load_timetable
+    // ==============================================================
+    // Replaces the timetable with one read from `input`. Every
+    // segment must exist in the network, and no service may use the
+    // name of an existing train or another service.
+    pub fn load_timetable(&mut self, input: &mut dyn BufRead) -> i32 {
+        let mut services: Vec<Service> = vec![];
+        let mut line_num = 0;
+        for result in input.lines() {
+            line_num += 1;
+            let text = match result {
+                Ok(l) => l,
+                Err(_) => return 5, // EIO
+            };
+            let line = text.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+
+            let service = match line.strip_prefix("service:").and_then(|rest| self.parse_service(rest)) {
+                Some(s) => s,
+                None => {
+                    println!("ERROR: line {line_num}: invalid service \"{line}\"");
+                    return 22; // EINVAL
+                }
+            };
+            if self.train_map.contains_key(&service.train)
+                || services.iter().any(|s| s.train == service.train) {
+                println!("ERROR: line {line_num}: train {} already exists", service.train);
+                return 17; // EEXIST
+            }
+            services.push(service);
+        }
+        self.timetable = services;
+        0
+    }
+
+    fn parse_service(&self, fields: &str) -> Option<Service> {
+        let fields: Vec<&str> = fields.split(',').map(|f| f.trim()).collect();
+        if fields.len() < 4 || fields[0].is_empty() { return None; }
+        let calls = fields[3..].iter().map(|f| parse_call(f)).collect::<Option<Vec<Call>>>()?;
+        let service = Service {
+            train: String::from(fields[0]),
+            entry: String::from(fields[1]),
+            departure: fields[2].parse().ok()?,
+            calls,
+            cancelled: false,
+        };
+        if !self.edge_map.contains_key(&service.entry)
+            || service.calls.iter().any(|c| !self.edge_map.contains_key(&c.segment)) {
+            return None;
+        }
+        Some(service)
+    }
+
+    pub fn get_timetable(&self) -> &Vec<Service> {
+        &self.timetable
+    }
+
+    // Returns true while some service is still to be dispatched at or
+    // after the current tick, so the simulation must keep running to
+    // place its train at the start of the next step.
+    pub fn has_pending_departures(&self) -> bool {
+        self.timetable.iter()
+            .any(|s| s.departure >= self.tick && !s.cancelled && !self.train_map.contains_key(&s.train))
+    }
+
+    // Places the train of each service that is due to depart and has
+    // not yet done so, if its entry segment is clear, and routes it
+    // through its calls, stopping at each for its dwell time. A
+    // service no route is found for is cancelled: its train is taken
+    // off again and the cancellation logged.
+    pub(super) fn dispatch_services(&mut self) {
+        let mut due = vec![];
+        for service in &self.timetable {
+            if service.departure > self.tick || service.cancelled
+                || self.train_map.contains_key(&service.train) {
+                continue;
+            }
+            let (dest, stops) = match service.calls.split_last() {
+                Some(split) => split,
+                None => continue,
+            };
+            let waypoints: Vec<Waypoint> = stops.iter()
+                .map(|c| Waypoint { segment: c.segment.clone(), dwell: c.dwell })
+                .collect();
+            due.push((service.train.clone(), service.entry.clone(), dest.segment.clone(), waypoints));
+        }
+
+        for (train, entry, dest, waypoints) in due {
+            // An earlier service may have just taken the segment.
+            match self.edge_map.get(&entry) {
+                Some(e) if e.train.is_empty() => (),
+                _ => continue,
+            }
+            if self.create_train(&train).is_none() { continue; }
+            let mut rc = self.place_train(&train, &entry, &dest);
+            if rc == 0 && !waypoints.is_empty() {
+                rc = self.set_train_waypoints(&train, waypoints);
+            }
+            if rc != 0 {
+                self.cancel_service(&train, &entry);
+            }
+        }
+    }
+
+    // Cancels every service that is due but has not departed, once
+    // nothing is left to clear its entry segment.
+    pub(super) fn cancel_stranded_services(&mut self) {
+        let stranded: Vec<(String, String)> = self.timetable.iter()
+            .filter(|s| s.departure <= self.tick && !s.cancelled && !self.train_map.contains_key(&s.train))
+            .map(|s| (s.train.clone(), s.entry.clone()))
+            .collect();
+        for (train, entry) in stranded {
+            self.cancel_service(&train, &entry);
+        }
+    }
+
+    fn cancel_service(&mut self, train: &str, entry: &String) {
+        self.train_map.remove(train);
+        if let Some(e) = self.edge_map.get_mut(entry) {
+            if e.train == train { e.set_train(""); }
+        }
+        if let Some(s) = self.timetable.iter_mut().find(|s| s.train == train) {
+            s.cancelled = true;
+        }
+        self.log_event(Event::Cancelled { train: train.to_string(), segment: entry.clone() });
+        self.update_all_signals();
+    }
+
+    // ==============================================================
+    // timetable_report
+    // ==============================================================
+    // Compares each service's departure and calls with the event log:
+    // the tick its train was placed, and the first tick it entered
+    // each of its calls in turn.
+    pub fn timetable_report(&self) -> Vec<ServiceReport> {
+        let mut reports = vec![];
+        for service in &self.timetable {
+            let mut calls = vec![CallReport {
+                kind: "depart", segment: service.entry.clone(),
+                scheduled: Some(service.departure), actual: None,
+            }];
+            for (ix, call) in service.calls.iter().enumerate() {
+                calls.push(CallReport {
+                    kind: if ix + 1 == service.calls.len() { "arrive" } else { "stop" },
+                    segment: call.segment.clone(),
+                    scheduled: call.time,
+                    actual: None,
+                });
+            }
+
+            // Calls are matched in order, from the latest placement.
+            let mut next = 0;
+            for entry in &self.events {
+                let segment = match &entry.event {
+                    Event::Placed { train, segment } if *train == service.train => {
+                        for c in calls.iter_mut() { c.actual = None; }
+                        calls[0].actual = Some(entry.tick);
+                        next = 1;
+                        segment
+                    }
+                    Event::Cancelled { train, .. } if *train == service.train => {
+                        for c in calls.iter_mut() { c.actual = None; }
+                        next = 0;
+                        continue;
+                    }
+                    Event::EnteredSegment { train, segment }
+                        if *train == service.train && next > 0 => segment,
+                    _ => continue,
+                };
+                if next < calls.len() && calls[next].segment == *segment {
+                    calls[next].actual = Some(entry.tick);
+                    next += 1;
+                }
+            }
+            reports.push(ServiceReport { train: service.train.clone(), calls });
+        }
+        reports
+    }
+
+    // Formats the timetable report as a table with one line per call.
+    pub fn format_timetable_report(&self) -> String {
+        let mut msg = format!("{:<12}{:<8}{:<12}{:>6}{:>8}{:>6}\n",
+                              "Train", "Call", "Segment", "Sched", "Actual", "Late");
+        for report in self.timetable_report() {
+            for (ix, call) in report.calls.iter().enumerate() {
+                let train = if ix == 0 { report.train.as_str() } else { "" };
+                let late = call.lateness().map_or(String::from("-"), |l| l.to_string());
+                msg += &format!("{:<12}{:<8}{:<12}{:>6}{:>8}{:>6}\n",
+                                train, call.kind, call.segment,
+                                format_tick(call.scheduled), format_tick(call.actual), late);
+            }
+        }
+        msg
+    }
+}
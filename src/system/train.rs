@@ -11,11 +11,45 @@ use system::System;
 //use super::edge;
 //use edge::Edge;
 
+// A segment the train must pass through on its way to its
+// destination, and the ticks it stops there for.
+//
+#[derive(PartialEq, Clone, Debug)]
+pub struct Waypoint {
+    pub segment: String,
+    pub dwell: u32,
+}
+
+impl Waypoint {
+    // Parses "segment" or "segment+dwell".
+    pub fn parse(text: &str) -> Option<Waypoint> {
+        let (segment, dwell) = match text.split_once('+') {
+            Some((s, d)) => (s.trim(), d.trim().parse().ok()?),
+            None => (text.trim(), 0),
+        };
+        if segment.is_empty() { return None; }
+        Some(Waypoint { segment: String::from(segment), dwell })
+    }
+}
+
 pub struct Train
 {
     pub name: String,
     pub edge: EdgeEnd,
     pub destination: String,
+    pub route: Vec<EdgeEnd>,
+    pub waypoints: Vec<Waypoint>,
+    // Index of the next waypoint to reach, and the ticks left to wait
+    // at the last one reached.
+    pub next_waypoint: usize,
+    pub dwell_remaining: u32,
+    // Ticks the train has been waiting to move on along its route.
+    pub wait_ticks: u32,
+    // The node the train last crossed, over which its tail may still
+    // stand; empty once it has reversed, and when it is placed.
+    pub last_node: String,
+    // The junction the train came off the track at, if it derailed.
+    pub derailed_at: Option<String>,
 }
 
 impl Train {
@@ -23,22 +57,97 @@ impl Train {
         &self.edge
     }
 
-    pub fn place_on_track(&mut self, start: &String, end: &String) {
-        self.edge.ee_edge = start.clone();
+    pub fn place_on_track(&mut self, start: &str, end: &str) {
+        self.edge.ee_edge = String::from(start);
         self.edge.ee_end = END_B; // get_optimal_route determines the final value.
-        self.destination = end.clone();
+        self.destination = String::from(end);
+        self.route.clear();
+        self.waypoints.clear();
+        self.next_waypoint = 0;
+        self.dwell_remaining = 0;
+        self.wait_ticks = 0;
+        self.last_node.clear();
+        self.derailed_at = None;
     }
 
-    pub fn show(&self, sys: &System) {
-        println!("Train: {}", &self.name);
-        if let Some(eref) = sys.get_edge(&self.edge.ee_edge) {
-            println!("  Location: track segment \"{}\"", eref.name);
-            println!("  Direction: toward segment end {}",
-                        if self.edge.ee_end == END_A { "A" } else { "B" });
+    // Sets the waypoints to pass through, in order, before the
+    // destination. A waypoint on the segment the train is already on
+    // counts as reached, and the train waits there first.
+    pub fn set_waypoints(&mut self, waypoints: Vec<Waypoint>) {
+        self.waypoints = waypoints;
+        self.next_waypoint = 0;
+        self.dwell_remaining = 0;
+        self.reach_waypoint();
+    }
+
+    // Called on entering a segment: if it is the next waypoint, the
+    // train starts its dwell there.
+    pub fn reach_waypoint(&mut self) {
+        if let Some(wp) = self.waypoints.get(self.next_waypoint) {
+            if wp.segment == self.edge.ee_edge {
+                self.dwell_remaining = wp.dwell;
+                self.next_waypoint += 1;
+            }
         }
     }
 
-    pub fn get_optimal_route(&mut self, _sys: &mut System) {
+    // The segments the route must pass through from here: the
+    // waypoints not yet reached, then the destination.
+    pub fn remaining_calls(&self) -> Vec<String> {
+        self.waypoints[self.next_waypoint..].iter()
+            .map(|w| w.segment.clone())
+            .chain(std::iter::once(self.destination.clone()))
+            .collect()
+    }
+
+    // The route is the sequence of positions the train will occupy
+    // after its current one, ending on the destination edge.
+    pub fn set_route(&mut self, route: Vec<EdgeEnd>) {
+        self.route = route;
     }
 
+    pub fn has_arrived(&self) -> bool {
+        self.edge.ee_edge == self.destination && self.next_waypoint == self.waypoints.len()
+    }
+
+    // Formats the train's location, direction, destination and the
+    // rest of its route, one item per line.
+    pub fn format(&self, sys: &System) -> String {
+        let mut msg = format!("Train: {}\n", &self.name);
+        if let Some(eref) = sys.get_edge(&self.edge.ee_edge) {
+            msg += &format!("  Location: track segment \"{}\"\n", eref.name);
+            msg += &format!("  Direction: toward segment end {}\n",
+                        if self.edge.ee_end == END_A { "A" } else { "B" });
+        }
+        if !self.destination.is_empty() {
+            msg += &format!("  Destination: track segment \"{}\"{}\n", &self.destination,
+                        if self.has_arrived() { " (arrived)" } else { "" });
+        }
+        if !self.waypoints.is_empty() {
+            let stops: Vec<String> = self.waypoints.iter().enumerate().map(|(ix, w)| {
+                let mut stop = w.segment.clone();
+                if w.dwell > 0 { stop += &format!(" (dwell {})", w.dwell); }
+                if ix < self.next_waypoint { stop += " (passed)"; }
+                stop
+            }).collect();
+            msg += &format!("  Waypoints: {}\n", stops.join(", "));
+        }
+        if self.dwell_remaining > 0 {
+            msg += &format!("  Stopped: {} tick(s) to wait\n", self.dwell_remaining);
+        }
+        if let Some(node) = &self.derailed_at {
+            msg += &format!("  Derailed: at junction \"{node}\"\n");
+        }
+        if !self.route.is_empty() {
+            // A step onto the same segment is a reversal.
+            let mut at = &self.edge.ee_edge;
+            let mut route = vec![];
+            for e in &self.route {
+                route.push(if e.ee_edge == *at { "reverse" } else { e.ee_edge.as_str() });
+                at = &e.ee_edge;
+            }
+            msg += &format!("  Route: {}\n", route.join(" -> "));
+        }
+        msg
+    }
 }
\ No newline at end of file
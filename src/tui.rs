@@ -0,0 +1,351 @@
+// tui.rs
+//
+// Description:
+//     Full screen terminal interface for the simulator. The schematic
+//     draws the network from its layout, with the signal aspects in
+//     color, the legs the junction switches are set to and the trains,
+//     and is redrawn as the simulation runs. Junction switches can be thrown and
+//     trains placed from the keyboard.
+//
+
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Context, Line as CanvasLine};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::common::*;
+use crate::generator;
+use crate::system::edge::Edge;
+use crate::system::System;
+
+// Time between steps while the simulation is running.
+const RUN_INTERVAL: Duration = Duration::from_millis(500);
+
+// Space around the network, in segment lengths and rows.
+const MARGIN: f64 = 0.5;
+// Rows above the track for segment names, and below it for trains.
+const LABEL_OFFSET: f64 = 0.3;
+// Fraction of a segment from a node to its signal head, and of a
+// junction leg highlighted from the junction.
+const SIGNAL_ALONG: f64 = 0.15;
+const LEG_HIGHLIGHT: f64 = 0.45;
+const SIGNAL_GLYPH: &str = "\u{25cf}";
+
+const HELP: &str =
+    " s step  r run/pause  \u{2191}\u{2193} select junction  t toggle  p place train  q quit";
+
+#[derive(PartialEq)]
+enum Mode {
+    Normal,
+    Running,
+    EnterStart,
+    EnterDest(String),
+}
+
+pub struct App {
+    mode: Mode,
+    junction: ListState,
+    input: String,
+    status: String,
+    steps: u32,
+    quit: bool,
+}
+
+impl App {
+    pub fn new() -> App {
+        App {
+            mode: Mode::Normal,
+            junction: ListState::default().with_selected(Some(0)),
+            input: String::new(),
+            status: String::from("Ready"),
+            steps: 0,
+            quit: false,
+        }
+    }
+
+    // ==============================================================
+    // handle_key
+    // ==============================================================
+    // Applies one key press to the application and the system.
+    pub fn handle_key(&mut self, sys: &mut System, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press { return; }
+        match &self.mode {
+            Mode::EnterStart | Mode::EnterDest(_) => self.handle_input_key(sys, key.code),
+            _ => self.handle_command_key(sys, key.code),
+        }
+    }
+
+    fn handle_command_key(&mut self, sys: &mut System, code: KeyCode) {
+        let junctions = sys.get_all_junctions();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('s') => {
+                self.mode = Mode::Normal;
+                self.step(sys);
+            }
+            KeyCode::Char('r') => {
+                if self.mode == Mode::Running {
+                    self.mode = Mode::Normal;
+                    self.status = String::from("Paused");
+                }
+                else {
+                    self.mode = Mode::Running;
+                    self.status = String::from("Running");
+                }
+            }
+            KeyCode::Up => {
+                let ix = self.junction.selected().unwrap_or(0);
+                self.junction.select(Some(ix.saturating_sub(1)));
+            }
+            KeyCode::Down => {
+                let ix = self.junction.selected().unwrap_or(0);
+                if ix + 1 < junctions.len() { self.junction.select(Some(ix + 1)); }
+            }
+            KeyCode::Char('t') => {
+                let ix = self.junction.selected().unwrap_or(0);
+                if let Some(name) = junctions.get(ix) {
+                    self.status = match sys.toggle_switch(name) {
+                        13 => format!("Junction {name} is clamped"),
+                        5 => format!("Junction {name} is stuck"),
+                        16 => format!("Junction {name} is already moving"),
+                        _ => format!("Toggled junction {name}"),
+                    };
+                }
+            }
+            KeyCode::Char('p') => {
+                self.mode = Mode::EnterStart;
+                self.input.clear();
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_input_key(&mut self, sys: &mut System, code: KeyCode) {
+        match code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.status = String::from("Cancelled");
+            }
+            KeyCode::Backspace => { self.input.pop(); }
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Enter => {
+                let name = self.input.trim().to_string();
+                self.input.clear();
+                if !sys.has_edge(&name) {
+                    self.status = format!("No such segment \"{name}\"");
+                    self.mode = Mode::Normal;
+                    return;
+                }
+                match std::mem::replace(&mut self.mode, Mode::Normal) {
+                    Mode::EnterStart => self.mode = Mode::EnterDest(name),
+                    Mode::EnterDest(start) => {
+                        self.status = match generator::place_train(sys, &start, &name) {
+                            Some(t) => format!("Placed {t} on {start} bound for {name}"),
+                            None => format!("Cannot place a train from {start} to {name}"),
+                        };
+                    }
+                    mode => self.mode = mode,
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn step(&mut self, sys: &mut System) {
+        let moved = sys.step();
+        if sys.is_halted() {
+            self.mode = Mode::Normal;
+            self.status = String::from("Safety violation, the simulation has been halted");
+        }
+        else if moved == 0 {
+            self.mode = Mode::Normal;
+            self.status = String::from("No train is able to move, the simulation is complete");
+        }
+        else {
+            self.steps += 1;
+            self.status = format!("Step {}: {moved} train(s) moved", self.steps);
+        }
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Draws the network on a canvas from the schematic layout: the track
+// segments with their names, the legs each junction switch is set to,
+// the signal heads in the color of their aspect and the trains on the
+// segments they occupy, scaled to fit `area`.
+fn schematic<'a>(sys: &'a System, title: String, area: Rect) -> Canvas<'a, impl Fn(&mut Context<'_>) + 'a> {
+    // The canvas Y axis runs upward, the layout rows downward.
+    let layout: HashMap<String, (f64, f64)> = sys.compute_layout().into_iter()
+        .map(|(name, p)| (name, (p.x, -p.y)))
+        .collect();
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (0.0, 1.0, 0.0, 0.0);
+    for &(x, y) in layout.values() {
+        min_x = f64::min(min_x, x);
+        max_x = f64::max(max_x, x);
+        min_y = f64::min(min_y, y);
+        max_y = f64::max(max_y, y);
+    }
+    // Labels are centered on a point, a cell's width per character.
+    let cell = (max_x - min_x + 2.0 * MARGIN) / f64::from(area.width.saturating_sub(2).max(1));
+    let centered = move |x: f64, text: &str| x - cell * text.chars().count() as f64 / 2.0;
+    Canvas::default()
+        .block(Block::bordered().title(title))
+        .marker(Marker::Braille)
+        .x_bounds([min_x - MARGIN, max_x + MARGIN])
+        .y_bounds([min_y - MARGIN, max_y + MARGIN])
+        .paint(move |ctx| {
+            let ends = |edge: &Edge, end: End| {
+                Some((*layout.get(&edge.ends[end].ns_node)?,
+                      *layout.get(&edge.ends[opposite_end(end)].ns_node)?))
+            };
+            let edges: Vec<&Edge> = sys.get_all_edges().iter().filter_map(|n| sys.get_edge(n)).collect();
+            for edge in &edges {
+                if let Some(((x1, y1), (x2, y2))) = ends(edge, END_A) {
+                    ctx.draw(&CanvasLine::new(x1, y1, x2, y2, Color::DarkGray));
+                }
+            }
+            // The common leg of each junction and the leg its switch is
+            // set to, out from the junction.
+            for name in sys.get_all_junctions() {
+                let (node, &(x, y)) = match (sys.get_node_ref(&name), layout.get(&name)) {
+                    (Some(n), Some(p)) => (n, p),
+                    _ => continue,
+                };
+                let active = match node.get_switch_pos() {
+                    JSwitch::JSwitchLeft => Some(SLOT_2),
+                    JSwitch::JSwitchRight => Some(SLOT_3),
+                    JSwitch::JSwitchNone => None,
+                };
+                for slot in [Some(SLOT_1), active].into_iter().flatten() {
+                    let ee = node.get_edge_end(slot);
+                    let far = sys.get_edge(&ee.ee_edge).and_then(|e| ends(e, ee.ee_end));
+                    if let Some((_, (fx, fy))) = far {
+                        ctx.draw(&CanvasLine::new(x, y, x + (fx - x) * LEG_HIGHLIGHT,
+                                                  y + (fy - y) * LEG_HIGHLIGHT, Color::Cyan));
+                    }
+                }
+            }
+            ctx.layer();
+            for edge in &edges {
+                let Some(((x1, y1), (x2, y2))) = ends(edge, END_A) else { continue };
+                let (mx, my) = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+                ctx.print(centered(mx, &edge.name), my + LABEL_OFFSET, edge.name.clone());
+                let train = edge.format_train(sys).trim().to_string();
+                if !train.is_empty() {
+                    ctx.print(centered(mx, &train), my - LABEL_OFFSET,
+                              Span::styled(train, Style::default().fg(Color::Yellow)));
+                }
+                for end in [END_A, END_B] {
+                    let Some(((px, py), (qx, qy))) = ends(edge, end) else { continue };
+                    let color = match edge.signal_glyph(end) {
+                        "R" => Color::Red,
+                        "G" => Color::Green,
+                        _ => continue,
+                    };
+                    ctx.print(px + (qx - px) * SIGNAL_ALONG, py + (qy - py) * SIGNAL_ALONG,
+                              Span::styled(SIGNAL_GLYPH, Style::default().fg(color)
+                                                                         .add_modifier(Modifier::BOLD)));
+                }
+            }
+        })
+}
+
+// ==================================================================
+// draw
+// ==================================================================
+// Draws the whole screen: the schematic, the junction and train
+// lists, and the status line.
+pub fn draw(frame: &mut Frame, sys: &System, app: &mut App) {
+    let [main, status] = Layout::vertical([Constraint::Min(5), Constraint::Length(3)])
+        .areas(frame.area());
+    let [network, side] = Layout::horizontal([Constraint::Min(40), Constraint::Length(34)])
+        .areas(main);
+    let [junctions, trains] = Layout::vertical([Constraint::Percentage(40),
+                                                Constraint::Percentage(60)])
+        .areas(side);
+
+    let title = format!(" Network ({} track segments) ", sys.edge_count());
+    frame.render_widget(schematic(sys, title, network), network);
+    draw_junctions(frame, sys, app, junctions);
+    draw_trains(frame, sys, trains);
+
+    let text = match &app.mode {
+        Mode::EnterStart => format!(" Starting segment: {}_", app.input),
+        Mode::EnterDest(start) => format!(" From {start} to segment: {}_", app.input),
+        _ => format!(" {}", app.status),
+    };
+    frame.render_widget(Paragraph::new(text).block(Block::bordered().title(HELP)), status);
+}
+
+fn draw_junctions(frame: &mut Frame, sys: &System, app: &mut App, area: Rect) {
+    let items: Vec<ListItem> = sys.get_all_junctions().iter().map(|name| {
+        let pos = match sys.get_node_ref(name).map(|n| n.get_switch_pos()) {
+            Some(JSwitch::JSwitchLeft) => "left",
+            Some(JSwitch::JSwitchRight) => "right",
+            _ => "none",
+        };
+        ListItem::new(format!("{name}  {pos}"))
+    }).collect();
+    let list = List::new(items)
+        .block(Block::bordered().title(" Junctions "))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(list, area, &mut app.junction);
+}
+
+fn draw_trains(frame: &mut Frame, sys: &System, area: Rect) {
+    let lines: Vec<Line> = sys.format_trains().lines()
+        .map(|l| Line::from(l.to_string()))
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(" Trains ")),
+                        area);
+}
+
+// ==================================================================
+// run
+// ==================================================================
+// Runs the terminal interface until the user quits.
+pub fn run(sys: &mut System) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let rc = event_loop(&mut terminal, sys);
+    ratatui::restore();
+    rc
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, sys: &mut System) -> io::Result<()> {
+    let mut app = App::new();
+    let mut last_step = Instant::now();
+    while !app.quit {
+        terminal.draw(|frame| draw(frame, sys, &mut app))?;
+
+        let timeout = if app.mode == Mode::Running {
+            RUN_INTERVAL.saturating_sub(last_step.elapsed())
+        }
+        else {
+            Duration::from_secs(60)
+        };
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                app.handle_key(sys, key);
+            }
+        }
+        if app.mode == Mode::Running && last_step.elapsed() >= RUN_INTERVAL {
+            app.step(sys);
+            last_step = Instant::now();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+// auto_switching.rs
+//
+// Tests for the simulator setting junction switches along the
+// routes of the trains, so the sample networks run end to end
+// without a switch thrown by hand.
+//
+
+mod common;
+use common::{load, name, csv};
+
+use signaling::common::JSwitch::JSwitchRight;
+use signaling::generator;
+use signaling::system::System;
+
+// Places the trains, runs the simulation once with the switches set
+// automatically, and checks every train arrives.
+fn runs_end_to_end(file: &str, trains: &[(&str, &str)]) -> System {
+    let mut sys = load(file);
+    sys.set_auto_switching(true);
+    for (start, dest) in trains {
+        assert!(generator::place_train(&mut sys, start, dest).is_some(),
+                "cannot place a train from {start} to {dest}");
+    }
+    sys.run();
+    for ix in 1..=trains.len() {
+        let train = sys.get_train(&format!("train{ix}")).unwrap();
+        assert!(train.has_arrived(), "train{ix} is at {}", train.get_position().ee_edge);
+    }
+    assert!(!sys.is_halted(), "{}", sys.format_safety_report());
+    sys
+}
+
+#[test]
+fn off_by_default() {
+    let mut sys = load("demo1");
+    assert!(!sys.get_auto_switching());
+    sys.create_train("train1");
+    sys.place_train("train1", &name("tseg001"), &name("tseg007"));
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+}
+
+#[test]
+fn demo1_end_to_end() {
+    let mut sys = runs_end_to_end("demo1", &[("tseg001", "tseg007")]);
+    assert!(sys.get_node(&name("node005")).unwrap().get_switch_pos() == JSwitchRight);
+    let log = csv(&sys);
+    assert!(log.contains("\n3,switch,,,,node005,right\n3,entered,train1,tseg006,,,\n"), "{log}");
+}
+
+#[test]
+fn demo2_end_to_end() {
+    runs_end_to_end("demo2", &[("tseg001", "tseg013"), ("tseg009", "tseg011")]);
+}
+
+#[test]
+fn demo3_end_to_end() {
+    runs_end_to_end("demo3", &[("tseg013", "tseg009"), ("tseg008", "tseg011")]);
+}
+
+#[test]
+fn switch_held_for_a_waiting_train() {
+    // train1 waits at node005 for the left fork, which train2 is on,
+    // so the switch is not thrown out from under it for train3.
+    let mut sys = load("demo1");
+    sys.set_auto_switching(true);
+    for (train, start, dest) in [("train1", "tseg003", "tseg004"),
+                                 ("train2", "tseg004", "tseg004"),
+                                 ("train3", "tseg006", "tseg001")] {
+        sys.create_train(train);
+        assert_eq!(sys.place_train(train, &name(start), &name(dest)), 0);
+    }
+    sys.step();
+    assert!(!csv(&sys).contains(",switch,"));
+    assert_eq!(sys.get_train("train3").unwrap().get_position().ee_edge, "tseg006");
+}
@@ -0,0 +1,59 @@
+// blocks.rs
+//
+// Tests for block sections: the track between one signal and the
+// next is a single block, and a signal shows red while any segment
+// of the block beyond it is occupied.
+//
+
+mod common;
+use common::{demo1, name, place};
+
+use signaling::common::END_B;
+use signaling::system::detection::Detection;
+use signaling::system::{create_system, System};
+
+fn segments(sys: &System) -> Vec<Vec<String>> {
+    sys.get_block_sections().into_iter().map(|b| b.segments).collect()
+}
+
+#[test]
+fn demo1_blocks() {
+    let sys = demo1();
+    assert_eq!(segments(&sys), vec![vec!["tseg001", "tseg002", "tseg003"],
+                                    vec!["tseg004", "tseg005"],
+                                    vec!["tseg006", "tseg007"]]);
+    assert_eq!(sys.get_block_of("tseg005").unwrap().name, "block002");
+    assert!(sys.get_block_of("nowhere").is_none());
+}
+
+#[test]
+fn signal_protects_the_whole_block() {
+    let mut sys = demo1();
+    assert!(!sys.get_edge(&name("tseg003")).unwrap().signals[END_B].signal_is_red());
+    place(&mut sys, "train1", "tseg005", "tseg005");
+    assert!(sys.get_edge(&name("tseg003")).unwrap().signals[END_B].signal_is_red());
+
+    let block = sys.get_block_of("tseg004").unwrap();
+    assert_eq!(sys.get_block_detection(&block), Detection::Occupied);
+    assert_eq!(sys.format_blocks(), "    block001: tseg001, tseg002, tseg003
+    block002: tseg004, tseg005 (occupied by train1)
+    block003: tseg006, tseg007
+TOTAL: 3 block sections
+");
+}
+
+#[test]
+fn blocks_follow_the_signals() {
+    let text = "\
+track: t1,1,n1,0,n2,0,sigA:N,sigB:N
+track: t2,1,n2,1,n3,0,sigA:N,sigB:N
+track: t3,1,n3,1,n4,0,sigA:N,sigB:N
+";
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    assert_eq!(segments(&sys), vec![vec!["t1", "t2", "t3"]]);
+
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut text.replace("t2,1,n2,1,n3,0,sigA:N", "t2,1,n2,1,n3,0,sigA:Y").as_bytes()), 0);
+    assert_eq!(segments(&sys), vec![vec!["t1"], vec!["t2", "t3"]]);
+}
@@ -0,0 +1,51 @@
+// common/mod.rs
+//
+// Helpers shared by the integration tests: loading the sample
+// networks, placing trains and reading back signals and the event
+// log. Each test crate uses only some of them.
+//
+
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use signaling::common::End;
+use signaling::system::{create_system, System};
+
+// Loads the named network from data/.
+pub fn load(file: &str) -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("data/{file}.txt"));
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0,
+               "failed to load {file}");
+    sys
+}
+
+// In demo1, tseg003 is the common edge of node005, tseg004 its left
+// fork and tseg006 its right fork; the switch starts out left.
+pub fn demo1() -> System {
+    load("demo1")
+}
+
+pub fn name(text: &str) -> String {
+    String::from(text)
+}
+
+// Creates a train and places it bound for the destination.
+pub fn place(sys: &mut System, train: &str, start: &str, dest: &str) {
+    sys.create_train(train);
+    assert_eq!(sys.place_train(train, &name(start), &name(dest)), 0);
+}
+
+pub fn is_red(sys: &System, segment: &str, end: End) -> bool {
+    sys.get_edge(&name(segment)).unwrap().signals[end].signal_is_red()
+}
+
+// The event log as CSV.
+pub fn csv(sys: &System) -> String {
+    let mut out = vec![];
+    assert_eq!(sys.write_events_csv(&mut out), 0);
+    String::from_utf8(out).unwrap()
+}
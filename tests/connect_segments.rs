@@ -0,0 +1,125 @@
+// connect_segments.rs
+//
+// Property based tests for System::connect_segments. Random sequences
+// of create_edge, connect_segments and toggle_switch_pos are applied
+// to a new system, and after every operation the network must remain
+// consistent: each edge end points at a node slot that points back at
+// it, and each node's type matches the slots it has occupied.
+//
+
+use proptest::prelude::*;
+
+use signaling::common::*;
+use signaling::system::{create_system, System};
+
+#[derive(Debug, Clone)]
+enum Op {
+    Create,
+    Connect(usize, End, usize, End),
+    Toggle(usize),
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        1 => Just(Op::Create),
+        4 => (any::<usize>(), 0..NUM_ENDS, any::<usize>(), 0..NUM_ENDS)
+                .prop_map(|(e1, x1, e2, x2)| Op::Connect(e1, x1, e2, x2)),
+        1 => any::<usize>().prop_map(Op::Toggle),
+    ]
+}
+
+fn apply(sys: &mut System, op: &Op) {
+    match *op {
+        Op::Create => {
+            assert!(sys.create_edge("").is_some());
+        }
+        Op::Connect(e1, x1, e2, x2) => {
+            let edges = sys.get_all_edges();
+            if edges.is_empty() { return; }
+            let s1 = EdgeEnd { ee_edge: edges[e1 % edges.len()].clone(), ee_end: x1 };
+            let s2 = EdgeEnd { ee_edge: edges[e2 % edges.len()].clone(), ee_end: x2 };
+            // Failing to connect is fine, as long as nothing breaks.
+            sys.connect_segments(&s1, &s2);
+        }
+        Op::Toggle(j) => {
+            let junctions = sys.get_all_junctions();
+            if junctions.is_empty() { return; }
+            let name = junctions[j % junctions.len()].clone();
+            if let Some(node) = sys.get_node(&name) {
+                node.toggle_switch_pos();
+            }
+        }
+    }
+}
+
+fn check_invariants(sys: &System) -> Result<(), TestCaseError> {
+    // Every edge end points at a node slot holding that edge end.
+    for name in sys.get_all_edges() {
+        let edge = sys.get_edge(&name).unwrap();
+        for end in [END_A, END_B] {
+            let ns = edge.get_node(end);
+            let node = sys.get_node_ref(&ns.ns_node);
+            prop_assert!(node.is_some(), "{} end {} has no node", name, end);
+            let back = node.unwrap().get_edge_end(ns.ns_slot);
+            prop_assert!(back.ee_edge == name && back.ee_end == end,
+                "{} end {} -> {} slot {} -> {} end {}",
+                name, end, ns.ns_node, ns.ns_slot, back.ee_edge, back.ee_end);
+        }
+    }
+
+    // Every occupied node slot points at an edge end holding that node
+    // slot, slots fill up in order, and the node type agrees.
+    for name in sys.get_all_nodes() {
+        let node = sys.get_node_ref(&name).unwrap();
+        let mut used = 0;
+        for slot in [SLOT_1, SLOT_2, SLOT_3] {
+            let ee = node.get_edge_end(slot);
+            if ee.ee_edge.is_empty() { continue; }
+            prop_assert!(slot == used, "{} slot {} is used but an earlier one is not", name, slot);
+            used += 1;
+            let edge = sys.get_edge(&ee.ee_edge);
+            prop_assert!(edge.is_some(), "{} slot {} names a missing edge", name, slot);
+            let back = edge.unwrap().get_node(ee.ee_end);
+            prop_assert!(back.ns_node == name && back.ns_slot == slot,
+                "{} slot {} -> {} end {} -> {} slot {}",
+                name, slot, ee.ee_edge, ee.ee_end, back.ns_node, back.ns_slot);
+        }
+        let expected = match used {
+            1 => NodeType::Terminator,
+            2 => NodeType::Continuation,
+            3 => NodeType::Junction,
+            _ => NodeType::Empty,
+        };
+        prop_assert!(node.get_node_type() == expected, "{} has the wrong node type", name);
+        prop_assert!(used > 0, "{} is not connected to anything", name);
+        if used == 3 {
+            prop_assert!(node.get_switch_pos() != JSwitch::JSwitchNone,
+                "{} is a junction with no switch position", name);
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn network_stays_consistent(ops in prop::collection::vec(op_strategy(), 1..60)) {
+        let mut sys = create_system();
+        sys.create_edge("");
+        for op in &ops {
+            apply(&mut sys, op);
+            check_invariants(&sys)?;
+        }
+    }
+
+    #[test]
+    fn segment_count_is_unchanged_by_connect(ops in prop::collection::vec(op_strategy(), 1..60)) {
+        let mut sys = create_system();
+        sys.create_edge("");
+        let mut created = 1;
+        for op in &ops {
+            if let Op::Create = op { created += 1; }
+            apply(&mut sys, op);
+        }
+        prop_assert_eq!(sys.get_all_edges().len(), created);
+    }
+}
@@ -0,0 +1,242 @@
+// data_files.rs
+//
+// Regression tests over the sample networks in data/. Each network
+// is loaded and saved again, driven through the interactive program
+// with a scripted train scenario whose output is compared against a
+// committed snapshot in tests/snapshots/, and run through the same
+// scenario using the library to check where the trains end up and
+// what the signals show. The text formatting of the network is also
+// checked directly through the library.
+//
+// To accept changed output, run the tests with UPDATE_SNAPSHOTS=1
+// and review the differences in tests/snapshots/ before committing.
+//
+
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use signaling::common::*;
+use signaling::generator;
+use signaling::system::{create_system, System};
+
+struct Scenario {
+    file: &'static str,
+    // Trains to place, as (start, destination) segments.
+    trains: &'static [(&'static str, &'static str)],
+    // The simulation is run once per phase, after toggling the
+    // junctions listed for that phase.
+    phases: &'static [&'static [&'static str]],
+    // Segment each train finishes on, at its destination.
+    positions: &'static [(&'static str, &'static str)],
+    // Segment each train is held on short of its destination.
+    held: &'static [(&'static str, &'static str)],
+    // Signal aspects at the end, as (segment, end, is red).
+    aspects: &'static [(&'static str, End, bool)],
+}
+
+const DEMO1: Scenario = Scenario {
+    file: "demo1",
+    trains: &[("tseg001", "tseg005"), ("tseg007", "tseg002")],
+    phases: &[&[], &["node005"]],
+    positions: &[("train1", "tseg005"), ("train2", "tseg002")],
+    held: &[],
+    aspects: &[("tseg003", END_B, false), ("tseg004", END_A, true),
+               ("tseg006", END_A, true)],
+};
+
+const DEMO2: Scenario = Scenario {
+    file: "demo2",
+    trains: &[("tseg001", "tseg013"), ("tseg009", "tseg011")],
+    phases: &[&["node004"], &["node004", "node005", "node009"]],
+    positions: &[("train1", "tseg013"), ("train2", "tseg011")],
+    held: &[],
+    aspects: &[("tseg002", END_B, false), ("tseg003", END_B, false),
+               ("tseg005", END_B, false), ("tseg010", END_A, false),
+               ("tseg011", END_B, true)],
+};
+
+const DEMO3: Scenario = Scenario {
+    file: "demo3",
+    trains: &[("tseg013", "tseg009"), ("tseg008", "tseg011")],
+    phases: &[&[], &["node003", "node005", "node009", "node012"]],
+    positions: &[("train1", "tseg009"), ("train2", "tseg011")],
+    held: &[],
+    aspects: &[("tseg005", END_A, false), ("tseg005", END_B, true),
+               ("tseg008", END_A, true), ("tseg009", END_B, false),
+               ("tseg012", END_A, true), ("tseg012", END_B, true),
+               ("tseg014", END_A, false), ("tseg014", END_B, false)],
+};
+
+const TEST1: Scenario = Scenario {
+    file: "test1",
+    trains: &[("tseg005", "tseg007"), ("tseg004", "tseg008")],
+    phases: &[&[], &["node006", "node012"]],
+    // train2 stands at its destination in the block beyond tseg009,
+    // so train1 is held at the signal there.
+    positions: &[("train2", "tseg008")],
+    held: &[("train1", "tseg009")],
+    aspects: &[("tseg001", END_A, true), ("tseg004", END_A, true),
+               ("tseg005", END_A, false), ("tseg006", END_B, true),
+               ("tseg009", END_B, true)],
+};
+
+fn data_path(file: &str) -> String {
+    format!("data/{file}.txt")
+}
+
+fn load(file: &str) -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(data_path(file));
+    let mut sys = create_system();
+    let rc = sys.deserialize(&mut BufReader::new(File::open(path).unwrap()));
+    assert_eq!(rc, 0, "failed to load {file}");
+    sys
+}
+
+// Saving a freshly loaded network must reproduce the file exactly.
+fn check_round_trip(file: &str) {
+    let sys = load(file);
+    let mut out = vec![];
+    assert_eq!(sys.serialize(&mut out), 0);
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(data_path(file));
+    let expected = fs::read_to_string(path).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+// Runs the scenario through the library and checks the outcome.
+fn check_scenario(sc: &Scenario) {
+    let mut sys = load(sc.file);
+    for (start, dest) in sc.trains {
+        assert!(generator::place_train(&mut sys, start, dest).is_some(),
+                "cannot place a train from {start} to {dest}");
+    }
+    for toggles in sc.phases {
+        for name in *toggles {
+            sys.get_node(&name.to_string()).unwrap().toggle_switch_pos();
+        }
+        sys.update_all_signals();
+        sys.run();
+    }
+
+    for (train, edge) in sc.positions {
+        let tref = sys.get_train(train).unwrap();
+        assert_eq!(&tref.get_position().ee_edge, edge, "{train} is in the wrong place");
+        assert!(tref.has_arrived(), "{train} has not arrived");
+        assert_eq!(sys.get_edge(&edge.to_string()).unwrap().get_train(), train);
+    }
+    for (train, edge) in sc.held {
+        let tref = sys.get_train(train).unwrap();
+        assert_eq!(&tref.get_position().ee_edge, edge, "{train} is in the wrong place");
+        assert!(!tref.has_arrived(), "{train} has arrived");
+    }
+    for (edge, end, red) in sc.aspects {
+        let eref = sys.get_edge(&edge.to_string()).unwrap();
+        assert!(eref.has_signal(*end), "{edge} has no signal at end {end}");
+        assert_eq!(eref.signals[*end].signal_is_red(), *red,
+                   "wrong aspect for {edge} end {end}");
+    }
+}
+
+// Builds the menu input that plays the scenario in the interactive
+// program, listing the network before and after.
+fn script(sc: &Scenario) -> String {
+    let junctions = load(sc.file).get_all_junctions();
+    let mut input = format!("1\n7\n{}\nr\n2\n3\n", data_path(sc.file));
+    for (start, dest) in sc.trains {
+        input += &format!("4\n\n{start}\n{dest}\n\n");
+    }
+    for toggles in sc.phases {
+        for name in *toggles {
+            let ix = junctions.iter().position(|j| j == name).unwrap() + 1;
+            input += &format!("1\n4\n{ix}\nr\n");
+        }
+        input += "6\n";
+    }
+    input += "2\n3\nq\n";
+    input
+}
+
+fn check_golden_output(sc: &Scenario) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_signaling"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(script(sc).as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let actual = String::from_utf8(output.stdout).unwrap();
+
+    let snapshot = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.txt", sc.file));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+        fs::write(&snapshot, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&snapshot)
+        .unwrap_or_else(|_| panic!("missing snapshot {}", snapshot.display()));
+    assert!(actual == expected,
+            "output differs from {}; rerun with UPDATE_SNAPSHOTS=1 to accept it\n{}",
+            snapshot.display(), actual);
+}
+
+#[test]
+fn demo1_round_trip() { check_round_trip(DEMO1.file); }
+#[test]
+fn demo1_scenario() { check_scenario(&DEMO1); }
+#[test]
+fn demo1_golden_output() { check_golden_output(&DEMO1); }
+
+#[test]
+fn demo2_round_trip() { check_round_trip(DEMO2.file); }
+#[test]
+fn demo2_scenario() { check_scenario(&DEMO2); }
+#[test]
+fn demo2_golden_output() { check_golden_output(&DEMO2); }
+
+#[test]
+fn demo3_round_trip() { check_round_trip(DEMO3.file); }
+#[test]
+fn demo3_scenario() { check_scenario(&DEMO3); }
+#[test]
+fn demo3_golden_output() { check_golden_output(&DEMO3); }
+
+#[test]
+fn test1_round_trip() { check_round_trip(TEST1.file); }
+#[test]
+fn test1_scenario() { check_scenario(&TEST1); }
+#[test]
+fn test1_golden_output() { check_golden_output(&TEST1); }
+
+#[test]
+fn demo1_formatting() {
+    let mut sys = load(DEMO1.file);
+    generator::place_train(&mut sys, "tseg001", "tseg005");
+    assert_eq!(sys.format_edges(), "\
+<term-> ||== _ tseg001 _ <==> tseg002   [o==o]-[o==o]\\ train1
+tseg001 <==> _ tseg002 _ <==> tseg003
+tseg002 <==> _ tseg003 G <=// tseg004
+tseg003 <=// R tseg004 _ <==> tseg005
+tseg004 <==> _ tseg005 _ ==|| <-term>
+tseg003 X=// R tseg006 _ <==> tseg007
+tseg006 <==> _ tseg007 _ ==|| <-term>
+TOTAL: 7 track segments
+");
+    let node = sys.get_node_ref(&String::from("node005")).unwrap();
+    assert_eq!(node.format(&sys),
+               "     node005:   node004,   node003,   node008  (switch: left)\n");
+    let edge = sys.get_edge(&String::from("tseg003")).unwrap();
+    assert_eq!(edge.format(&sys, END_B), "tseg003 G <=// tseg004\n");
+    assert_eq!(sys.format_trains(), "\
+Train: train1
+  Location: track segment \"tseg001\"
+  Direction: toward segment end B
+  Destination: track segment \"tseg005\"
+  Route: tseg002 -> tseg003 -> tseg004 -> tseg005
+");
+}
@@ -0,0 +1,63 @@
+// detection.rs
+//
+// Tests for the train detection layer: each segment's detector
+// follows the trains on it, signals read the detectors, and detector
+// faults override them to either side.
+//
+
+mod common;
+use common::{demo1, name, place, is_red};
+
+use signaling::common::END_B;
+use signaling::system::detection::Detection;
+use signaling::system::faults::Fault;
+use signaling::system::safety::Invariant;
+
+#[test]
+fn detectors_follow_the_trains() {
+    let mut sys = demo1();
+    place(&mut sys, "train1", "tseg001", "tseg002");
+    assert_eq!(sys.get_detection("tseg001"), Some(Detection::Occupied));
+    assert_eq!(sys.get_detection("tseg002"), Some(Detection::Clear));
+    assert_eq!(sys.get_detection("nowhere"), None);
+    sys.run();
+    assert_eq!(sys.get_detection("tseg001"), Some(Detection::Clear));
+    assert_eq!(sys.get_detection("tseg002"), Some(Detection::Occupied));
+}
+
+#[test]
+fn falsely_occupied_detector_fails_safe() {
+    let mut sys = demo1();
+    assert!(!is_red(&sys, "tseg003", END_B));
+    sys.inject_fault(Fault::FalseOccupied { segment: name("tseg004") });
+    assert_eq!(sys.get_detection("tseg004"), Some(Detection::Occupied));
+    assert!(is_red(&sys, "tseg003", END_B));
+    assert_eq!(sys.format_detection(), "\
+Segment     Detector  Train
+tseg004     occupied  -
+");
+}
+
+#[test]
+fn detector_failed_clear_is_caught() {
+    let mut sys = demo1();
+    place(&mut sys, "train1", "tseg004", "tseg004");
+    place(&mut sys, "train2", "tseg003", "tseg004");
+    assert!(is_red(&sys, "tseg003", END_B));
+
+    let fault = Fault::FalseClear { segment: name("tseg004") };
+    assert_eq!(sys.inject_fault(fault.clone()), 0);
+    assert_eq!(sys.get_detection("tseg004"), Some(Detection::Clear));
+    assert!(!is_red(&sys, "tseg003", END_B));
+    let report = sys.format_fault_report();
+    assert!(report.contains("tseg004 falsely clear"), "{report}");
+    assert!(report.contains("train2"), "{report}");
+
+    // The signal lets train2 in on top of train1.
+    sys.step();
+    assert!(sys.is_halted());
+    assert_eq!(sys.get_violations()[0].invariant, Invariant::SharedSegment);
+    let mut out = vec![];
+    sys.write_events_csv(&mut out);
+    assert!(String::from_utf8(out).unwrap().contains("\n0,fault,,tseg004,,,false-clear\n"));
+}
@@ -0,0 +1,111 @@
+// events.rs
+//
+// Tests for the simulation event log: what gets recorded as trains
+// move, wait at signals and arrive, and as switches are thrown, and
+// the CSV and JSON lines output.
+//
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use signaling::generator;
+use signaling::system::{create_system, System};
+
+// demo1 with one train bound through the junction and another coming
+// the other way that is held at the junction's signal.
+fn demo1_run() -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo1.txt");
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    assert!(sys.get_events().is_empty());
+    generator::place_train(&mut sys, "tseg001", "tseg007");
+    generator::place_train(&mut sys, "tseg005", "tseg002");
+    sys.run();
+    assert_eq!(sys.toggle_switch(&String::from("node005")), 0);
+    sys.run();
+    sys
+}
+
+fn csv(sys: &System) -> String {
+    let mut out = vec![];
+    assert_eq!(sys.write_events_csv(&mut out), 0);
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn run_is_logged_as_csv() {
+    let sys = demo1_run();
+    assert_eq!(sys.get_tick(), 6);
+    assert_eq!(csv(&sys), "\
+tick,event,train,segment,end,node,state
+0,placed,train1,tseg001,,,
+0,signal,,tseg004,A,,red
+0,placed,train2,tseg005,,,
+0,signal,,tseg003,B,,red
+1,entered,train1,tseg002,,,
+1,entered,train2,tseg004,,,
+2,entered,train1,tseg003,,,
+2,held,train2,tseg004,A,,
+3,held,train1,tseg003,B,,
+3,held,train2,tseg004,A,,
+3,switch,,,,node005,right
+3,signal,,tseg003,B,,green
+4,entered,train1,tseg006,,,
+4,held,train2,tseg004,A,,
+4,signal,,tseg003,B,,red
+4,signal,,tseg006,A,,green
+5,entered,train1,tseg007,,,
+5,arrived,train1,tseg007,,,
+5,held,train2,tseg004,A,,
+6,held,train2,tseg004,A,,
+");
+}
+
+#[test]
+fn csv_fields_are_quoted() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo1.txt");
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    let name = "the \"flyer\", late";
+    sys.create_train(name);
+    assert_eq!(sys.place_train(name, &String::from("tseg001"), &String::from("tseg002")), 0);
+    let text = csv(&sys);
+    assert_eq!(text.lines().nth(1), Some(r#"0,placed,"the ""flyer"", late",tseg001,,,"#));
+}
+
+#[test]
+fn run_is_logged_as_json_lines() {
+    let sys = demo1_run();
+    let mut out = vec![];
+    assert_eq!(sys.write_events_json(&mut out), 0);
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), sys.get_events().len());
+    assert_eq!(lines[0], r#"{"tick":0,"event":"placed","train":"train1","segment":"tseg001"}"#);
+    assert_eq!(lines[4], r#"{"tick":1,"event":"entered","train":"train1","segment":"tseg002"}"#);
+    assert!(lines.contains(&r#"{"tick":3,"event":"switch","node":"node005","state":"right"}"#));
+    assert!(lines.contains(&r#"{"tick":5,"event":"arrived","train":"train1","segment":"tseg007"}"#));
+    assert_eq!(lines[3],
+               r#"{"tick":0,"event":"signal","segment":"tseg003","end":"B","state":"red"}"#);
+}
+
+#[test]
+fn log_starts_over_with_the_network() {
+    let mut sys = demo1_run();
+    sys.clear_events();
+    assert!(sys.get_events().is_empty());
+    assert_eq!(sys.get_tick(), 6);
+    sys.reset_track_network();
+    assert_eq!(sys.get_tick(), 0);
+    assert_eq!(csv(&sys), "tick,event,train,segment,end,node,state\n");
+}
+
+#[test]
+fn only_junctions_can_be_toggled() {
+    let mut sys = demo1_run();
+    let count = sys.get_events().len();
+    assert_eq!(sys.toggle_switch(&String::from("node001")), 22);
+    assert_eq!(sys.toggle_switch(&String::from("nowhere")), 2);
+    assert_eq!(sys.get_events().len(), count);
+}
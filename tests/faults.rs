@@ -0,0 +1,137 @@
+// faults.rs
+//
+// Tests for failure injection: stuck switches, dark signals and
+// track circuits falsely reporting a train, how the signals fail
+// safe to red, and which trains are affected.
+//
+
+mod common;
+use common::{demo1, name, is_red, csv};
+
+use signaling::common::{END_A, END_B};
+use signaling::common::JSwitch::{JSwitchNone, JSwitchRight};
+use signaling::system::faults::Fault;
+
+#[test]
+fn stuck_switch_fails_safe() {
+    let mut sys = demo1();
+    let stuck = Fault::SwitchStuck { node: name("node005"), position: JSwitchNone };
+    assert!(!is_red(&sys, "tseg003", END_B));
+    assert_eq!(sys.inject_fault(stuck.clone()), 0);
+    assert!(is_red(&sys, "tseg003", END_B));
+    assert!(is_red(&sys, "tseg004", END_A));
+    assert_eq!(sys.toggle_switch(&name("node005")), 5);
+    assert!(sys.find_route("tseg001", "tseg005").is_none());
+
+    assert_eq!(sys.inject_fault(stuck.clone()), 17);
+    assert_eq!(sys.inject_fault(Fault::SwitchStuck { node: name("node001"), position: JSwitchNone }), 22);
+    assert_eq!(sys.inject_fault(Fault::SwitchStuck { node: name("nowhere"), position: JSwitchNone }), 2);
+
+    assert_eq!(sys.clear_fault(&stuck), 0);
+    assert_eq!(sys.clear_fault(&stuck), 2);
+    assert!(!is_red(&sys, "tseg003", END_B));
+    assert_eq!(sys.toggle_switch(&name("node005")), 0);
+}
+
+#[test]
+fn cleared_switch_goes_back() {
+    let mut sys = demo1();
+    sys.toggle_switch(&name("node005"));
+    let stuck = Fault::SwitchStuck { node: name("node005"), position: JSwitchNone };
+    assert_eq!(sys.inject_fault(stuck.clone()), 0);
+    assert_eq!(sys.clear_fault(&stuck), 0);
+    assert!(sys.get_node(&name("node005")).unwrap().get_switch_pos() == JSwitchRight);
+    let log = csv(&sys);
+    assert!(log.contains("\n0,repaired,,,,node005,switch-stuck\n0,switch,,,,node005,right\n"), "{log}");
+
+    // It is not moved under a train standing on the junction.
+    sys.create_train("train1");
+    sys.place_train("train1", &name("tseg001"), &name("tseg007"));
+    while sys.get_train("train1").unwrap().get_position().ee_edge != "tseg006" {
+        assert!(sys.step() > 0);
+    }
+    assert_eq!(sys.inject_fault(stuck.clone()), 0);
+    assert_eq!(sys.clear_fault(&stuck), 16);
+    assert_eq!(sys.get_faults().len(), 1);
+    sys.step();
+    assert_eq!(sys.clear_fault(&stuck), 0);
+    assert!(sys.get_node(&name("node005")).unwrap().get_switch_pos() == JSwitchRight);
+}
+
+#[test]
+fn clamped_switch_is_not_stuck() {
+    let mut sys = demo1();
+    sys.toggle_switch(&name("node005"));
+    assert_eq!(sys.set_clamped(&name("node005"), true), 0);
+    let stuck = Fault::SwitchStuck { node: name("node005"), position: JSwitchNone };
+    assert_eq!(sys.inject_fault(stuck.clone()), 16);
+    assert!(sys.get_node(&name("node005")).unwrap().get_switch_pos() == JSwitchRight);
+    let mut out = vec![];
+    assert_eq!(sys.serialize(&mut out), 0);
+    assert!(String::from_utf8(out).unwrap().contains("\nclamp: node005,right\n"));
+
+    // Nor is a stuck switch clamped, as it has no position.
+    assert_eq!(sys.set_clamped(&name("node005"), false), 0);
+    assert_eq!(sys.inject_fault(stuck), 0);
+    assert_eq!(sys.set_clamped(&name("node005"), true), 16);
+}
+
+#[test]
+fn dark_signal_is_red() {
+    let mut sys = demo1();
+    let dark = Fault::LampOut { segment: name("tseg003"), end: END_B };
+    assert_eq!(sys.inject_fault(dark.clone()), 0);
+    assert!(is_red(&sys, "tseg003", END_B));
+    assert_eq!(sys.inject_fault(Fault::LampOut { segment: name("tseg002"), end: END_B }), 22);
+
+    sys.create_train("train1");
+    sys.place_train("train1", &name("tseg001"), &name("tseg005"));
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+    assert_eq!(sys.clear_fault(&dark), 0);
+    sys.run();
+    assert!(sys.get_train("train1").unwrap().has_arrived());
+}
+
+#[test]
+fn false_occupancy_holds_trains() {
+    let mut sys = demo1();
+    sys.create_train("train1");
+    sys.place_train("train1", &name("tseg001"), &name("tseg005"));
+    sys.create_train("train2");
+    sys.place_train("train2", &name("tseg007"), &name("tseg006"));
+    let ghost = Fault::FalseOccupied { segment: name("tseg004") };
+    assert_eq!(sys.inject_fault(ghost.clone()), 0);
+    assert!(is_red(&sys, "tseg003", END_B));
+
+    let affected = sys.affected_trains();
+    assert_eq!(affected.len(), 1);
+    assert_eq!((affected[0].train.as_str(), &affected[0].fault), ("train1", &ghost));
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+    assert!(sys.get_train("train2").unwrap().has_arrived());
+    assert!(sys.get_edge(&name("tseg004")).unwrap().train.is_empty());
+}
+
+#[test]
+fn report_and_log() {
+    let mut sys = demo1();
+    assert_eq!(sys.format_fault_report(), "No faults in effect\n");
+    sys.create_train("train1");
+    sys.place_train("train1", &name("tseg001"), &name("tseg007"));
+    sys.inject_fault(Fault::SwitchStuck { node: name("node005"), position: JSwitchNone });
+    sys.inject_fault(Fault::FalseOccupied { segment: name("tseg005") });
+    assert_eq!(sys.format_fault_report(), "\
+Fault                           Trains affected
+switch stuck at node005         train1
+tseg005 falsely occupied        -
+");
+    sys.clear_fault(&Fault::FalseOccupied { segment: name("tseg005") });
+    let log = csv(&sys);
+    assert!(log.contains("\n0,fault,,,,node005,switch-stuck\n"), "{log}");
+    assert!(log.contains("\n0,fault,,tseg005,,,false-occupied\n"), "{log}");
+    assert!(log.contains("\n0,repaired,,tseg005,,,false-occupied\n"), "{log}");
+
+    sys.reset_track_network();
+    assert!(sys.get_faults().is_empty());
+}
@@ -0,0 +1,142 @@
+// layout.rs
+//
+// Tests for the automatic schematic layout: positions follow the
+// track, junction forks go to their own side, no two nodes share a
+// spot, and coordinates fixed in the network file are kept and saved.
+//
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use signaling::common::*;
+use signaling::generator::random::{generate_scenario, RandomConfig};
+use signaling::system::{create_system, System};
+
+fn load(file: &str) -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("data/{file}.txt"));
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    sys
+}
+
+fn load_str(text: &str) -> (System, i32) {
+    let mut sys = create_system();
+    let rc = sys.deserialize(&mut text.as_bytes());
+    (sys, rc)
+}
+
+fn position(sys: &System, node: &str) -> Point {
+    sys.get_node_ref(&node.to_string()).unwrap().position
+        .unwrap_or_else(|| panic!("{node} has no position"))
+}
+
+fn check_all_placed_apart(sys: &System) {
+    let mut seen: Vec<(String, Point)> = vec![];
+    for name in sys.get_all_nodes() {
+        let pos = position(sys, &name);
+        for (other, p) in &seen {
+            assert!(*p != pos, "{name} and {other} are both at {pos:?}");
+        }
+        seen.push((name, pos));
+    }
+}
+
+#[test]
+fn demo1_follows_the_track() {
+    let mut sys = load("demo1");
+    sys.layout_network();
+
+    // node001 -> node002 -> node004 -> node005 is a straight line.
+    for (ix, node) in ["node001", "node002", "node004", "node005"].iter().enumerate() {
+        assert_eq!(position(&sys, node), Point { x: ix as f64, y: 0.0 });
+    }
+    // At junction node005 the left fork goes up, the right fork down,
+    // and both then run parallel to the main line.
+    assert_eq!(position(&sys, "node003"), Point { x: 4.0, y: -1.0 });
+    assert_eq!(position(&sys, "node007"), Point { x: 5.0, y: -1.0 });
+    assert_eq!(position(&sys, "node008"), Point { x: 4.0, y: 1.0 });
+    assert_eq!(position(&sys, "node009"), Point { x: 5.0, y: 1.0 });
+}
+
+#[test]
+fn segment_length_sets_spacing() {
+    let (mut sys, rc) = load_str("\
+track: t1,3,n1,0,n2,0,sigA:N,sigB:N
+track: t2,2,n2,1,n3,0,sigA:N,sigB:N
+");
+    assert_eq!(rc, 0);
+    sys.layout_network();
+    assert_eq!(position(&sys, "n1"), Point { x: 0.0, y: 0.0 });
+    assert_eq!(position(&sys, "n2"), Point { x: 3.0, y: 0.0 });
+    assert_eq!(position(&sys, "n3"), Point { x: 5.0, y: 0.0 });
+}
+
+#[test]
+fn every_node_is_placed_apart() {
+    for file in ["demo1", "demo2", "demo3", "test1"] {
+        let mut sys = load(file);
+        sys.layout_network();
+        check_all_placed_apart(&sys);
+    }
+    for seed in 0..20 {
+        let mut sys = generate_scenario(seed, &RandomConfig::default());
+        sys.layout_network();
+        check_all_placed_apart(&sys);
+    }
+}
+
+#[test]
+fn separate_networks_are_stacked() {
+    let (mut sys, rc) = load_str("\
+track: t1,1,n1,0,n2,0,sigA:N,sigB:N
+track: t2,1,n3,0,n4,0,sigA:N,sigB:N
+");
+    assert_eq!(rc, 0);
+    sys.layout_network();
+    assert_eq!(position(&sys, "n2"), Point { x: 1.0, y: 0.0 });
+    assert_eq!(position(&sys, "n3"), Point { x: 0.0, y: 2.0 });
+    assert_eq!(position(&sys, "n4"), Point { x: 1.0, y: 2.0 });
+}
+
+#[test]
+fn fixed_positions_are_kept_and_saved() {
+    let text = "\
+track: t1,1,n1,0,n2,0,sigA:N,sigB:N
+track: t2,2,n2,1,n3,0,sigA:N,sigB:Y
+node: n2,10,4.5
+";
+    let (mut sys, rc) = load_str(text);
+    assert_eq!(rc, 0);
+    sys.layout_network();
+    assert_eq!(position(&sys, "n2"), Point { x: 10.0, y: 4.5 });
+    assert!(sys.get_node_ref(&String::from("n2")).unwrap().fixed_position);
+    // The rest of the line is laid out around the fixed node.
+    let n1 = position(&sys, "n1");
+    let n3 = position(&sys, "n3");
+    assert_eq!((n1.x - 10.0).abs(), 1.0);
+    assert_eq!((n3.x - 10.0).abs(), 2.0);
+    assert!(n1.x != n3.x);
+
+    // Only the fixed position is written back out.
+    let mut out = vec![];
+    assert_eq!(sys.serialize(&mut out), 0);
+    assert_eq!(String::from_utf8(out).unwrap(), text);
+
+    // Releasing the node hands it back to the automatic layout.
+    assert_eq!(sys.set_node_position(&String::from("n2"), None), 0);
+    sys.layout_network();
+    assert_eq!(position(&sys, "n2"), Point { x: 1.0, y: 0.0 });
+}
+
+#[test]
+fn bad_node_lines_are_rejected() {
+    let track = "track: t1,1,n1,0,n2,0,sigA:N,sigB:N\n";
+    for line in ["node: n1,1", "node: n1,x,2", "node: ,1,2", "node: n9,1,2"] {
+        let (sys, rc) = load_str(&format!("{track}{line}\n"));
+        assert_eq!(rc, 22, "\"{line}\" was accepted");
+        assert_eq!(sys.edge_count(), 0);
+    }
+    let (_, rc) = load_str(&format!("{track}node: n1,1,2\nnode: n1,3,4\n"));
+    assert_eq!(rc, 22);
+}
@@ -0,0 +1,88 @@
+// maintenance.rs
+//
+// Tests for segments taken out of service and junctions clamped in
+// one position: the signals protecting them, route planning around
+// them, and saving them in the network file.
+//
+
+mod common;
+use common::{demo1, name, is_red};
+
+use signaling::common::{END_A, END_B};
+use signaling::common::JSwitch::JSwitchRight;
+use signaling::system::create_system;
+
+#[test]
+fn signals_protect_a_closed_segment() {
+    let mut sys = demo1();
+    sys.toggle_switch(&name("node005"));
+    assert!(!is_red(&sys, "tseg003", END_B));
+    assert_eq!(sys.set_closed(&name("tseg006"), true), 0);
+    assert!(is_red(&sys, "tseg003", END_B));
+    assert_eq!(sys.set_closed(&name("tseg006"), false), 0);
+    assert!(!is_red(&sys, "tseg003", END_B));
+    assert_eq!(sys.set_closed(&name("nowhere"), true), 2);
+}
+
+#[test]
+fn routes_avoid_closed_segments() {
+    let mut sys = demo1();
+    sys.set_closed(&name("tseg006"), true);
+    assert!(sys.find_route("tseg001", "tseg007").is_none());
+    assert!(sys.find_route("tseg001", "tseg005").is_some());
+
+    // A train already under way stops short of it.
+    sys.set_closed(&name("tseg006"), false);
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &name("tseg001"), &name("tseg007")), 0);
+    sys.toggle_switch(&name("node005"));
+    sys.set_closed(&name("tseg006"), true);
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+}
+
+#[test]
+fn clamped_junction_holds_its_position() {
+    let mut sys = demo1();
+    assert_eq!(sys.set_clamped(&name("node005"), true), 0);
+    assert_eq!(sys.toggle_switch(&name("node005")), 13);
+    assert!(sys.find_route("tseg001", "tseg007").is_none());
+    assert!(sys.find_route("tseg001", "tseg005").is_some());
+    assert!(sys.find_route("tseg006", "tseg002").is_none());
+
+    assert_eq!(sys.set_clamped(&name("node005"), false), 0);
+    assert_eq!(sys.toggle_switch(&name("node005")), 0);
+    assert!(sys.find_route("tseg001", "tseg007").is_some());
+    assert_eq!(sys.set_clamped(&name("node001"), true), 22);
+    assert_eq!(sys.set_clamped(&name("nowhere"), true), 2);
+}
+
+#[test]
+fn flags_are_saved() {
+    let mut sys = demo1();
+    sys.set_closed(&name("tseg002"), true);
+    sys.toggle_switch(&name("node005"));
+    sys.set_clamped(&name("node005"), true);
+    let mut out = vec![];
+    assert_eq!(sys.serialize(&mut out), 0);
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("\nclosed: tseg002\nclamp: node005,right\n"), "{text}");
+
+    let mut copy = create_system();
+    assert_eq!(copy.deserialize(&mut text.as_bytes()), 0);
+    assert!(copy.get_edge(&name("tseg002")).unwrap().closed);
+    let node = copy.get_node(&name("node005")).unwrap();
+    assert!(node.clamped);
+    assert!(node.get_switch_pos() == JSwitchRight);
+    assert!(is_red(&copy, "tseg004", END_A) == is_red(&sys, "tseg004", END_A));
+}
+
+#[test]
+fn bad_flags_are_rejected() {
+    let track = "track: t1,1,n1,0,n2,0,sigA:N,sigB:N\n";
+    for line in ["closed: t9", "clamp: n1,left", "clamp: n9,left", "clamp: n1,up", "clamp: n1"] {
+        let mut sys = create_system();
+        let text = format!("{track}{line}\n");
+        assert_ne!(sys.deserialize(&mut text.as_bytes()), 0, "\"{line}\" was accepted");
+    }
+}
@@ -0,0 +1,112 @@
+// replay.rs
+//
+// Tests for recording a simulation run and exporting it as SVG: the
+// recording must follow the run tick by tick, and the exported
+// pictures must show each tick in turn.
+//
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use signaling::common::*;
+use signaling::generator;
+use signaling::system::{create_system, System};
+
+// demo3 with two trains that pass through its junctions, which are
+// set for them automatically.
+fn demo3_with_trains() -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo3.txt");
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    sys.set_auto_switching(true);
+    assert!(generator::place_train(&mut sys, "tseg013", "tseg009").is_some());
+    assert!(generator::place_train(&mut sys, "tseg008", "tseg011").is_some());
+    sys
+}
+
+#[test]
+fn recording_follows_the_run() {
+    let steps = demo3_with_trains().run();
+    let mut sys = demo3_with_trains();
+    let replay = sys.run_recorded();
+
+    assert!(steps > 0);
+    assert_eq!(replay.frames.len() as u32, steps + 1);
+    for (ix, frame) in replay.frames.iter().enumerate() {
+        assert_eq!(frame.tick, ix as u32);
+    }
+
+    let first = &replay.frames[0];
+    assert_eq!(first.trains, vec![(String::from("train1"), String::from("tseg013")),
+                                  (String::from("train2"), String::from("tseg008"))]);
+    // The last frame is the state the run finished in.
+    let last = replay.frames.last().unwrap();
+    let now = sys.capture_state(last.tick);
+    assert_eq!(last.trains, now.trains);
+    assert_eq!(last.signals, now.signals);
+    assert!(last.switches == now.switches);
+
+    // Some train moves on every tick, and never more than a segment.
+    for pair in replay.frames.windows(2) {
+        assert!(pair[0].trains != pair[1].trains, "nothing moved on tick {}", pair[1].tick);
+        for ((_, from), (_, to)) in pair[0].trains.iter().zip(&pair[1].trains) {
+            if from == to { continue; }
+            let moves = sys.get_next_moves(&EdgeEnd { ee_edge: from.clone(), ee_end: END_A })
+                .into_iter()
+                .chain(sys.get_next_moves(&EdgeEnd { ee_edge: from.clone(), ee_end: END_B }));
+            assert!(moves.map(|m| m.ee_edge).any(|e| e == *to), "{from} to {to} is not a move");
+        }
+    }
+}
+
+#[test]
+fn recording_captures_signals_and_switches() {
+    let mut sys = demo3_with_trains();
+    let replay = sys.run_recorded();
+    let first = &replay.frames[0];
+    assert_eq!(first.switches.len(), sys.get_all_junctions().len());
+    assert!(first.switches.iter().all(|(_, pos)| *pos == JSwitch::JSwitchLeft));
+    let signals: usize = sys.get_all_edges().iter()
+        .map(|e| { let e = sys.get_edge(e).unwrap(); e.has_signal(END_A) as usize
+                                                     + e.has_signal(END_B) as usize })
+        .sum();
+    assert_eq!(first.signals.len(), signals);
+    // A signal somewhere changes aspect as the trains pass.
+    let last = replay.frames.last().unwrap();
+    assert!(first.signals != last.signals);
+}
+
+#[test]
+fn frames_show_each_tick() {
+    let mut sys = demo3_with_trains();
+    let replay = sys.run_recorded();
+    let frames = sys.replay_frames(&replay);
+    assert_eq!(frames.len(), replay.frames.len());
+    for (ix, frame) in frames.iter().enumerate() {
+        assert!(frame.starts_with("<svg "));
+        assert!(frame.contains(&format!(">Tick {ix}</text>")));
+        assert_eq!(frame.matches(">train").count(), 2);
+    }
+    assert!(frames[0] != *frames.last().unwrap());
+}
+
+#[test]
+fn animation_plays_every_tick_in_turn() {
+    let mut sys = demo3_with_trains();
+    let replay = sys.run_recorded();
+    let count = replay.frames.len();
+    let svg = sys.replay_to_svg(&replay, 0.5);
+
+    // The track is drawn once, under one layer per tick.
+    assert_eq!(svg.matches("<g id=\"tracks\">").count(), 1);
+    assert_eq!(svg.matches("<animate ").count(), count);
+    for tick in 0..count {
+        assert!(svg.contains(&format!("<g id=\"tick{tick}\" visibility=\"hidden\">")));
+    }
+    let total = format!("dur=\"{:.3}s\"", 0.5 * count as f64);
+    assert_eq!(svg.matches(&total).count(), count);
+    assert!(svg.contains("values=\"visible;hidden\" keyTimes=\"0;"));
+    assert!(svg.contains("values=\"hidden;visible\" keyTimes=\"0;"));
+    assert!(svg.ends_with("</svg>\n"));
+}
@@ -0,0 +1,109 @@
+// reroute.rs
+//
+// Tests for alternative routes and for the simulator rerouting a
+// train that has been held for the reroute wait.
+//
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+mod common;
+use common::{csv, demo1, name, place};
+
+use signaling::common::{EdgeEnd, END_B};
+use signaling::system::faults::Fault;
+use signaling::system::{create_system, System};
+
+// Four segments in a ring, with a train on r1 bound for r3 the
+// short way round through r2, and then another train parked on r2.
+fn ring() -> System {
+    let (mut sys, text) = (create_system(), "\
+track: r1,1,k1,0,k2,0,sigA:N,sigB:N
+track: r2,1,k2,1,k3,0,sigA:N,sigB:N
+track: r3,1,k3,1,k4,0,sigA:N,sigB:N
+track: r4,1,k4,1,k1,1,sigA:N,sigB:N
+reverse: r1
+");
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &String::from("r1"), &String::from("r3")), 0);
+    assert_eq!(segments(&sys.get_train("train1").unwrap().route), vec!["r2", "r3"]);
+    sys.create_train("blocker");
+    assert_eq!(sys.place_train("blocker", &String::from("r2"), &String::from("r2")), 0);
+    sys
+}
+
+fn segments(route: &[EdgeEnd]) -> Vec<&str> {
+    route.iter().map(|e| e.ee_edge.as_str()).collect()
+}
+
+#[test]
+fn alternatives_cheapest_first() {
+    let (mut sys, text) = (create_system(), "\
+track: c,1,n0,0,j1,0,sigA:N,sigB:N
+track: a,5,j1,1,j2,1,sigA:N,sigB:N
+track: b,1,j1,2,j2,2,sigA:N,sigB:N
+track: d,1,j2,0,n9,0,sigA:N,sigB:N
+");
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    let routes = sys.find_alternative_routes("c", &[String::from("d")], 2);
+    let routes: Vec<Vec<&str>> = routes.iter().map(|r| segments(r)).collect();
+    assert_eq!(routes, vec![vec!["c", "b", "d"], vec!["c", "a", "d"]]);
+    assert_eq!(sys.find_alternative_routes("c", &[String::from("d")], 1).len(), 1);
+    assert!(sys.find_alternative_routes("c", &[String::from("x")], 3).is_empty());
+}
+
+#[test]
+fn held_train_waits_without_a_reroute_wait() {
+    let mut sys = ring();
+    assert_eq!(sys.get_reroute_wait(), 0);
+    sys.run();
+    let train = sys.get_train("train1").unwrap();
+    assert_eq!(train.get_position().ee_edge, "r1");
+    assert!(!csv(&sys).contains("rerouted"));
+}
+
+#[test]
+fn held_train_is_rerouted_after_the_wait() {
+    let mut sys = ring();
+    sys.set_reroute_wait(2);
+    sys.run();
+    let log = csv(&sys);
+    let train = sys.get_train("train1").unwrap();
+    assert!(train.has_arrived());
+    assert!(log.contains("\n3,rerouted,train1,r1,,,\n3,reversed,train1,r1,,,\n"), "{log}");
+    assert!(log.contains("\n4,entered,train1,r4,,,\n5,entered,train1,r3,,,\n5,arrived"), "{log}");
+}
+
+#[test]
+fn no_reroute_without_a_way_round() {
+    let mut sys = ring();
+    sys.set_reversing(&String::from("r1"), false);
+    sys.set_reroute_wait(1);
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "r1");
+    assert_eq!(sys.reroute_train("train1"), 16);
+    assert_eq!(sys.reroute_train("nobody"), 2);
+    assert!(!sys.has_pending_reroutes());
+}
+
+#[test]
+fn held_at_a_red_signal_is_not_rerouted_onto_the_same_way() {
+    // train1 is held at the dark signal with the way beyond it clear,
+    // and there is no other way to go, so the run comes to an end.
+    let mut sys = demo1();
+    place(&mut sys, "train1", "tseg003", "tseg004");
+    sys.inject_fault(Fault::LampOut { segment: name("tseg003"), end: END_B });
+    sys.set_reroute_wait(1);
+
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        sys.run();
+        done.send(sys).unwrap();
+    });
+    let sys = finished.recv_timeout(Duration::from_secs(10)).expect("the run did not come to an end");
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+    assert!(!csv(&sys).contains("rerouted"));
+    assert!(!sys.has_pending_reroutes());
+}
@@ -0,0 +1,90 @@
+// reversal.rs
+//
+// Tests for trains reversing: the planner may reverse a train at a
+// terminator or on a designated reversing segment, at a cost, and
+// the simulator carries the reversal out as a step of its own.
+//
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use signaling::common::EdgeEnd;
+use signaling::system::{create_system, System};
+
+fn demo1() -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo1.txt");
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    sys
+}
+
+fn segments(route: &[EdgeEnd]) -> Vec<&str> {
+    route.iter().map(|e| e.ee_edge.as_str()).collect()
+}
+
+#[test]
+fn reverse_at_a_terminator() {
+    // tseg006 and tseg004 are the two forks of node005, so the way
+    // from one to the other is back to the buffer stop at node001.
+    let sys = demo1();
+    let route = sys.find_route("tseg006", "tseg004").unwrap();
+    assert_eq!(segments(&route), vec!["tseg006", "tseg003", "tseg002", "tseg001",
+                                      "tseg001", "tseg002", "tseg003", "tseg004"]);
+    assert_ne!(route[3].ee_end, route[4].ee_end);
+}
+
+#[test]
+fn reverse_on_a_designated_segment() {
+    let mut sys = demo1();
+    assert_eq!(sys.set_reversing(&String::from("tseg003"), true), 0);
+    assert_eq!(sys.set_reversing(&String::from("nowhere"), true), 2);
+    let route = sys.find_route("tseg006", "tseg004").unwrap();
+    assert_eq!(segments(&route), vec!["tseg006", "tseg003", "tseg003", "tseg004"]);
+}
+
+#[test]
+fn reversing_is_avoided_when_there_is_a_way_round() {
+    let mut sys = demo1();
+    sys.set_reversing(&String::from("tseg002"), true);
+    let route = sys.find_route("tseg001", "tseg007").unwrap();
+    assert_eq!(segments(&route), vec!["tseg001", "tseg002", "tseg003", "tseg006", "tseg007"]);
+}
+
+#[test]
+fn train_reverses_into_the_other_fork() {
+    let mut sys = demo1();
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &String::from("tseg006"), &String::from("tseg004")), 0);
+    let text = sys.get_train("train1").unwrap().format(&sys);
+    assert!(text.contains("Route: tseg003 -> tseg002 -> tseg001 -> reverse -> tseg002"), "{text}");
+
+    // Out through the right fork, then back in through the left.
+    sys.toggle_switch(&String::from("node005"));
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+    sys.toggle_switch(&String::from("node005"));
+    sys.run();
+    assert!(sys.get_train("train1").unwrap().has_arrived());
+
+    let mut out = vec![];
+    sys.write_events_csv(&mut out);
+    let csv = String::from_utf8(out).unwrap();
+    assert!(csv.contains("4,reversed,train1,tseg001,,,\n"), "{csv}");
+}
+
+#[test]
+fn reversing_segments_are_saved() {
+    let mut sys = demo1();
+    sys.set_reversing(&String::from("tseg003"), true);
+    let mut out = vec![];
+    assert_eq!(sys.serialize(&mut out), 0);
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("\nreverse: tseg003\n"));
+
+    let mut copy = create_system();
+    assert_eq!(copy.deserialize(&mut text.as_bytes()), 0);
+    assert!(copy.get_edge(&String::from("tseg003")).unwrap().reversing);
+    assert!(!copy.get_edge(&String::from("tseg002")).unwrap().reversing);
+    assert_ne!(copy.deserialize(&mut "reverse: nowhere\n".as_bytes()), 0);
+}
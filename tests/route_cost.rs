@@ -0,0 +1,99 @@
+// route_cost.rs
+//
+// Tests for the route cost model: routes are planned to the lowest
+// weighted cost of length, junction legs, reversals and occupied
+// segments, and the cost of a train's route can be broken down.
+//
+
+use signaling::common::EdgeEnd;
+use signaling::system::route::{CostWeights, RouteCost};
+use signaling::system::{create_system, System};
+
+// Two ways from c to d between a pair of junctions: the long
+// straight leg a, or the short diverging leg b.
+fn loop_network() -> System {
+    let (mut sys, text) = (create_system(), "\
+track: c,1,n0,0,j1,0,sigA:N,sigB:N
+track: a,5,j1,1,j2,1,sigA:N,sigB:N
+track: b,1,j1,2,j2,2,sigA:N,sigB:N
+track: d,1,j2,0,n9,0,sigA:N,sigB:N
+");
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    sys
+}
+
+fn segments(route: &[EdgeEnd]) -> Vec<&str> {
+    route.iter().map(|e| e.ee_edge.as_str()).collect()
+}
+
+#[test]
+fn shortest_by_length() {
+    let sys = loop_network();
+    let route = sys.find_route("c", "d").unwrap();
+    assert_eq!(segments(&route), vec!["c", "b", "d"]);
+    let cost = sys.route_cost(&route, "");
+    assert_eq!(cost, RouteCost { length: 2, straight: 0, diverging: 2, reversals: 0, occupied: 0 });
+    assert_eq!(cost.total(sys.get_cost_weights()), 4);
+}
+
+#[test]
+fn weights_change_the_route() {
+    let mut sys = loop_network();
+    sys.set_cost_weights(CostWeights { length: 0, ..CostWeights::default() });
+    assert_eq!(segments(&sys.find_route("c", "d").unwrap()), vec!["c", "a", "d"]);
+    sys.set_cost_weights(CostWeights { length: 1, straight: 0, diverging: 3, reversal: 5, occupied: 0 });
+    assert_eq!(segments(&sys.find_route("c", "d").unwrap()), vec!["c", "a", "d"]);
+}
+
+#[test]
+fn large_weights_do_not_overflow() {
+    let mut sys = loop_network();
+    sys.set_cost_weights(CostWeights { length: 4_000_000_000, ..CostWeights::default() });
+    let route = sys.find_route("c", "d").unwrap();
+    assert_eq!(segments(&route), vec!["c", "b", "d"]);
+    assert_eq!(sys.route_cost(&route, "").total(sys.get_cost_weights()), u32::MAX);
+}
+
+#[test]
+fn occupied_segments_are_avoided() {
+    let mut sys = loop_network();
+    sys.create_train("blocker");
+    sys.place_train("blocker", &String::from("b"), &String::from("b"));
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &String::from("c"), &String::from("d")), 0);
+    let cost = sys.train_route_cost("train1").unwrap();
+    assert_eq!((cost.length, cost.straight, cost.occupied), (6, 2, 0));
+
+    // With no penalty the short way is taken, train or not.
+    sys.set_cost_weights(CostWeights { occupied: 0, ..CostWeights::default() });
+    assert_eq!(sys.get_optimal_route("train1"), 0);
+    assert_eq!(sys.train_route_cost("train1").unwrap().occupied, 1);
+}
+
+#[test]
+fn reversals_are_counted() {
+    let mut sys = loop_network();
+    // The only way from a to b is back out through c or d.
+    let route = sys.find_route("a", "b").unwrap();
+    assert_eq!(route.len(), 4);
+    assert_eq!(route[1].ee_edge, route[2].ee_edge);
+    let cost = sys.route_cost(&route, "");
+    assert_eq!((cost.length, cost.straight, cost.diverging, cost.reversals), (2, 1, 1, 1));
+    assert_eq!(cost.total(sys.get_cost_weights()), 2 + 1 + 5);
+    sys.set_cost_weights(CostWeights { reversal: 50, ..CostWeights::default() });
+    assert_eq!(cost.total(sys.get_cost_weights()), 2 + 1 + 50);
+}
+
+#[test]
+fn breakdown_is_a_table() {
+    let sys = loop_network();
+    let route = sys.find_route("c", "d").unwrap();
+    assert_eq!(sys.route_cost(&route, "").format(sys.get_cost_weights()), "\
+Route cost: 4
+  length           2 x 1        2
+  straight         0 x 0        0
+  diverging        2 x 1        2
+  reversals        0 x 5        0
+  occupied         0 x 10       0
+");
+}
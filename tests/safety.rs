@@ -0,0 +1,112 @@
+// safety.rs
+//
+// Tests for the safety invariant checker: ordinary runs are clean,
+// moves the signals should not have let through are caught, and a
+// violation is reported and halts the simulation.
+//
+
+mod common;
+use common::{demo1, name, place};
+
+use signaling::common::END_B;
+use signaling::common::JSwitch::JSwitchLeft;
+use signaling::system::safety::Invariant;
+
+#[test]
+fn ordinary_run_is_clean() {
+    let mut sys = demo1();
+    sys.create_train("train1");
+    sys.create_train("train2");
+    assert_eq!(sys.place_train("train1", &name("tseg001"), &name("tseg007")), 0);
+    assert_eq!(sys.place_train("train2", &name("tseg004"), &name("tseg004")), 0);
+    sys.step();
+    sys.step();
+    // Held at a red signal, train1 has the switch thrown ahead of it.
+    assert_eq!(sys.toggle_switch(&name("node005")), 0);
+    sys.run();
+    assert!(sys.get_train("train1").unwrap().has_arrived());
+    assert!(!sys.is_halted());
+    assert_eq!(sys.format_safety_report(), "No safety violations\n");
+}
+
+#[test]
+fn junction_thrown_under_a_train() {
+    let mut sys = demo1();
+    sys.toggle_switch(&name("node005"));
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &name("tseg003"), &name("tseg007")), 0);
+    sys.step();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg006");
+    assert_eq!(sys.junction_occupants("node005"), vec!["train1"]);
+    sys.toggle_switch(&name("node005"));
+    sys.step();
+
+    assert!(sys.is_halted());
+    let violations = sys.get_violations();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].invariant, Invariant::ThrownOccupied);
+    assert_eq!(violations[0].tick, 2);
+    let report = sys.format_safety_report();
+    assert!(report.contains("node005 was thrown with train1 on it"), "{report}");
+}
+
+#[test]
+fn trains_sharing_a_segment() {
+    let mut sys = demo1();
+    sys.create_train("train1");
+    sys.create_train("train2");
+    sys.place_train("train1", &name("tseg001"), &name("tseg005"));
+    sys.place_train("train2", &name("tseg005"), &name("tseg005"));
+    sys.get_train_mut("train2").unwrap().edge.ee_edge = name("tseg001");
+    assert_eq!(sys.check_safety(), 1);
+    let v = &sys.get_violations()[0];
+    assert_eq!((v.invariant, v.detail.as_str()), (Invariant::SharedSegment, "train1, train2 share tseg001"));
+}
+
+#[test]
+fn passed_a_stale_green() {
+    let mut sys = demo1();
+    place(&mut sys, "train2", "tseg005", "tseg005");
+    place(&mut sys, "train1", "tseg003", "tseg004");
+    // The signal shows green although train2 holds the block beyond.
+    sys.get_edge_mut(&name("tseg003")).unwrap().get_signal(END_B).set_signal_red(false);
+    assert_eq!(sys.step(), 1);
+    let v = &sys.get_violations()[0];
+    assert_eq!(v.invariant, Invariant::PassedRed);
+    assert_eq!(v.detail, "train1 passed the red signal at tseg003 end B into tseg004");
+}
+
+#[test]
+fn switch_changed_behind_a_clear_signal() {
+    let mut sys = demo1();
+    sys.toggle_switch(&name("node005"));
+    place(&mut sys, "train1", "tseg006", "tseg001");
+    // The switch goes back without the signal being updated.
+    sys.get_node(&name("node005")).unwrap().set_switch_pos(JSwitchLeft);
+    assert_eq!(sys.step(), 1);
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+    // Set against the train, the switch should also have held its signal.
+    let found: Vec<_> = sys.get_violations().iter().map(|v| (v.invariant, v.detail.as_str())).collect();
+    assert_eq!(found, vec![
+        (Invariant::PassedRed, "train1 passed the red signal at tseg006 end A into tseg003"),
+        (Invariant::WrongLeg, "train1 crossed node005 from tseg006 into tseg003, but it was set for tseg004"),
+    ]);
+}
+
+#[test]
+fn halted_until_reset() {
+    let mut sys = demo1();
+    sys.create_train("train1");
+    sys.create_train("train2");
+    sys.place_train("train1", &name("tseg001"), &name("tseg005"));
+    sys.place_train("train2", &name("tseg006"), &name("tseg006"));
+    sys.get_train_mut("train2").unwrap().edge.ee_edge = name("tseg002");
+    assert_eq!(sys.step(), 1);
+    assert!(sys.is_halted());
+    assert_eq!(sys.step(), 0);
+    assert_eq!(sys.run(), 0);
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg002");
+
+    sys.reset_track_network();
+    assert!(!sys.is_halted());
+}
@@ -0,0 +1,112 @@
+// signal_advice.rs
+//
+// Tests for the signal placement advisor: signals are recommended
+// for facing junctions and for blocks longer than the headway, the
+// ones no movement needs are found redundant, and the advice can be
+// applied to the network.
+//
+
+use std::fs;
+use std::path::Path;
+
+mod common;
+use common::demo1;
+
+use signaling::common::{END_A, END_B};
+use signaling::system::advisor::{format_advice, AdviceKind, Movement};
+use signaling::system::{create_system, System};
+
+// demo1, with its network file text changed as given.
+fn demo1_with(from: &str, to: &str) -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo1.txt");
+    let text = fs::read_to_string(path).unwrap();
+    assert!(text.contains(from));
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut text.replace(from, to).as_bytes()), 0);
+    sys
+}
+
+fn movement(start: &str, dest: &str) -> Movement {
+    Movement { start: String::from(start), calls: vec![String::from(dest)] }
+}
+
+#[test]
+fn facing_junction_is_protected() {
+    let sys = demo1_with("node005,0,sigA:N,sigB:Y", "node005,0,sigA:N,sigB:N");
+    let advice = sys.advise_signals(&[movement("tseg001", "tseg007")], 0);
+    assert_eq!(advice[0].kind, AdviceKind::FacingJunction);
+    assert_eq!((advice[0].segment.as_str(), advice[0].end), ("tseg003", END_B));
+    assert_eq!(advice[0].detail, "tseg003 faces the points of node005");
+
+    // Trailing through the junction needs no signal of its own.
+    let advice = sys.advise_signals(&[movement("tseg007", "tseg001")], 0);
+    assert!(advice.iter().all(|a| a.kind != AdviceKind::FacingJunction));
+}
+
+#[test]
+fn long_blocks_and_redundant_signals() {
+    let sys = demo1();
+    let movements = [movement("tseg001", "tseg007")];
+    assert_eq!(format_advice(&sys.advise_signals(&movements, 2)), "\
+Signal advice:
+  place   tseg002 end B  long-block       the block from tseg001 runs past the headway of 2 tick(s)
+  remove  tseg004 end A  redundant        no movement passes it
+  remove  tseg006 end A  redundant        no movement passes it
+TOTAL: 1 to place, 2 to remove
+");
+    assert!(sys.advise_signals(&movements, 3).iter().all(|a| a.kind != AdviceKind::LongBlock));
+
+    // A signal that divides two blocks is kept, and one the other way
+    // is passed.
+    let both = [movement("tseg001", "tseg007"), movement("tseg005", "tseg001")];
+    let removed: Vec<String> = sys.advise_signals(&both, 0).into_iter()
+        .filter(|a| a.is_removal())
+        .map(|a| a.segment)
+        .collect();
+    assert_eq!(removed, vec!["tseg006"]);
+}
+
+#[test]
+fn signal_at_a_buffer_stop() {
+    let sys = demo1_with("node007,0,sigA:N,sigB:N", "node007,0,sigA:N,sigB:Y");
+    let advice = sys.advise_signals(&[movement("tseg004", "tseg005"), movement("tseg005", "tseg001")], 0);
+    let stop = advice.iter().find(|a| a.segment == "tseg005").unwrap();
+    assert_eq!((stop.kind, stop.end), (AdviceKind::Redundant, END_B));
+    assert_eq!(stop.detail, "it stands at the buffer stop node007");
+}
+
+#[test]
+fn advice_is_applied() {
+    let mut sys = demo1();
+    let movements = [movement("tseg001", "tseg007")];
+    let advice = sys.advise_signals(&movements, 2);
+    assert_eq!(sys.apply_signal_advice(&advice), 3);
+    assert!(sys.get_edge(&String::from("tseg002")).unwrap().has_signal(END_B));
+    assert!(!sys.get_edge(&String::from("tseg004")).unwrap().has_signal(END_A));
+    assert!(sys.advise_signals(&movements, 2).is_empty());
+    assert_eq!(format_advice(&[]), "No change to the signals is advised\n");
+
+    let mut out = vec![];
+    assert_eq!(sys.serialize(&mut out), 0);
+    assert!(String::from_utf8(out).unwrap().contains("track: tseg002,1,node002,1,node004,0,sigA:N,sigB:Y\n"));
+
+    // The movement still runs on the new signals.
+    sys.set_auto_switching(true);
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &String::from("tseg001"), &String::from("tseg007")), 0);
+    sys.run();
+    assert!(sys.get_train("train1").unwrap().has_arrived());
+    assert!(!sys.is_halted(), "{}", sys.format_safety_report());
+}
+
+#[test]
+fn timetable_gives_the_movements() {
+    let mut sys = demo1();
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo1_timetable.txt");
+    let text = fs::read_to_string(path).unwrap();
+    assert_eq!(sys.load_timetable(&mut text.as_bytes()), 0);
+    let movements = sys.timetable_movements();
+    assert_eq!(movements.len(), 3);
+    assert_eq!(movements[0].start, "tseg001");
+    assert_eq!(movements[0].calls, vec!["tseg003", "tseg005"]);
+}
@@ -0,0 +1,101 @@
+// stats.rs
+//
+// Tests for the statistics computed from a simulation run.
+//
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use signaling::generator;
+use signaling::system::{create_system, System};
+
+fn load(file: &str) -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("data/{file}.txt"));
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    sys
+}
+
+// demo1 with one train bound through the junction and another coming
+// the other way that is held at the junction's signal.
+fn demo1_run() -> System {
+    let mut sys = load("demo1");
+    generator::place_train(&mut sys, "tseg001", "tseg007");
+    generator::place_train(&mut sys, "tseg005", "tseg002");
+    sys.run();
+    sys.toggle_switch(&String::from("node005"));
+    sys.run();
+    sys
+}
+
+#[test]
+fn trains_segments_and_junctions() {
+    let stats = demo1_run().compute_statistics();
+    assert_eq!(stats.ticks, 6);
+
+    let t1 = &stats.trains[0];
+    assert_eq!((t1.name.as_str(), t1.travel_ticks, t1.held_ticks, t1.segments, t1.distance),
+               ("train1", Some(5), 1, 4, 4));
+    let t2 = &stats.trains[1];
+    assert_eq!((t2.name.as_str(), t2.travel_ticks, t2.held_ticks, t2.segments, t2.distance),
+               ("train2", None, 5, 1, 1));
+
+    let occupied: Vec<(&str, u32)> = stats.segments.iter()
+        .map(|s| (s.name.as_str(), s.occupied_ticks))
+        .collect();
+    assert_eq!(occupied, vec![("tseg001", 0), ("tseg002", 1), ("tseg003", 2), ("tseg004", 6),
+                              ("tseg005", 0), ("tseg006", 1), ("tseg007", 2)]);
+    assert_eq!(stats.segments[3].occupancy, 100.0);
+    assert!((stats.segments[2].occupancy - 100.0 / 3.0).abs() < 1e-9);
+
+    assert_eq!(stats.junctions.len(), 1);
+    assert_eq!((stats.junctions[0].name.as_str(), stats.junctions[0].throws), ("node005", 1));
+}
+
+#[test]
+fn distance_follows_segment_length() {
+    let (mut sys, text) = (create_system(), "\
+track: t1,2,n1,0,n2,0,sigA:N,sigB:N
+track: t2,5,n2,1,n3,0,sigA:N,sigB:N
+track: t3,3,n3,1,n4,0,sigA:N,sigB:N
+");
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    generator::place_train(&mut sys, "t1", "t3");
+    sys.run();
+    let stats = sys.compute_statistics();
+    assert_eq!(stats.trains[0].distance, 8);
+    assert_eq!(stats.trains[0].travel_ticks, Some(2));
+}
+
+#[test]
+fn placing_again_starts_a_new_journey() {
+    let mut sys = demo1_run();
+    let name = String::from("train1");
+    assert_eq!(sys.place_train(&name, &String::from("tseg007"), &String::from("tseg006")), 0);
+    sys.run();
+    let t1 = &sys.compute_statistics().trains[0];
+    assert_eq!((t1.travel_ticks, t1.held_ticks, t1.segments), (Some(1), 0, 1));
+}
+
+#[test]
+fn report_is_a_table() {
+    let report = demo1_run().compute_statistics().format();
+    assert!(report.starts_with("Simulation statistics after 6 tick(s)\n"));
+    assert!(report.contains("\
+Train         Travel    Held  Segments  Distance
+train1             5       1         4         4
+train2             -       5         1         1
+"));
+    assert!(report.contains("tseg003          33.3%\n"));
+    assert!(report.contains("node005            1\n"));
+}
+
+#[test]
+fn nothing_run_is_all_zero() {
+    let stats = load("demo2").compute_statistics();
+    assert_eq!(stats.ticks, 0);
+    assert!(stats.trains.is_empty());
+    assert!(stats.segments.iter().all(|s| s.occupancy == 0.0));
+    assert!(stats.junctions.iter().all(|j| j.throws == 0));
+}
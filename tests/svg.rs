@@ -0,0 +1,103 @@
+// svg.rs
+//
+// Tests for the SVG snapshot of the network: segments, the active
+// junction leg, signal aspects and trains all appear in the picture
+// and follow the state of the simulation.
+//
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use signaling::generator;
+use signaling::system::{create_system, System};
+
+fn load_demo1() -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo1.txt");
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    sys
+}
+
+// Returns the element of `svg` that contains `text`.
+fn element<'a>(svg: &'a str, text: &str) -> &'a str {
+    svg.lines().find(|l| l.contains(text))
+        .unwrap_or_else(|| panic!("no element with \"{text}\""))
+}
+
+fn attr<'a>(element: &'a str, name: &str) -> &'a str {
+    let start = element.find(&format!(" {name}=\"")).unwrap() + name.len() + 3;
+    let len = element[start..].find('"').unwrap();
+    &element[start..start + len]
+}
+
+// Returns the point the highlighted leg at a junction runs toward.
+fn highlighted_leg_end(svg: &str) -> Vec<(f64, f64)> {
+    svg.lines().filter(|l| l.starts_with("<line") && l.contains("#1e90ff"))
+        .map(|l| (attr(l, "x2").parse().unwrap(), attr(l, "y2").parse().unwrap()))
+        .collect()
+}
+
+#[test]
+fn draws_the_whole_network() {
+    let sys = load_demo1();
+    let svg = sys.to_svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    for n in 1..=7 {
+        assert!(svg.contains(&format!(">tseg00{n}</text>")), "tseg00{n} is missing");
+    }
+    assert_eq!(svg.matches("<line").count(), 7 + 2 + 3);  // tracks, legs, masts
+    assert!(svg.contains("<title>node005</title>"));
+}
+
+#[test]
+fn signals_follow_their_aspect() {
+    let mut sys = load_demo1();
+    let svg = sys.to_svg();
+    assert_eq!(attr(element(&svg, "<title>tseg003 end B</title>"), "fill"), "limegreen");
+    assert_eq!(attr(element(&svg, "<title>tseg006 end A</title>"), "fill"), "red");
+
+    sys.get_node(&String::from("node005")).unwrap().toggle_switch_pos();
+    sys.update_all_signals();
+    let svg = sys.to_svg();
+    assert_eq!(attr(element(&svg, "<title>tseg004 end A</title>"), "fill"), "red");
+    assert_eq!(attr(element(&svg, "<title>tseg006 end A</title>"), "fill"), "limegreen");
+}
+
+#[test]
+fn junction_highlights_the_active_leg() {
+    let mut sys = load_demo1();
+    let left = highlighted_leg_end(&sys.to_svg());
+    sys.get_node(&String::from("node005")).unwrap().toggle_switch_pos();
+    let right = highlighted_leg_end(&sys.to_svg());
+
+    // The common leg stays lit, the other leg changes side: the left
+    // fork is drawn above the main line and the right fork below it.
+    assert_eq!(left.len(), 2);
+    assert_eq!(right.len(), 2);
+    assert_eq!(left[0], right[0]);
+    assert!(left[1].1 < left[0].1);
+    assert!(right[1].1 > right[0].1);
+}
+
+#[test]
+fn trains_are_labelled_where_they_are() {
+    let mut sys = load_demo1();
+    assert!(!sys.to_svg().contains(">train1</text>"));
+    generator::place_train(&mut sys, "tseg001", "tseg005");
+    let before = sys.to_svg();
+    let label = element(&before, ">train1</text>").to_string();
+    sys.run();
+    let after = sys.to_svg();
+    let moved = element(&after, ">train1</text>");
+    assert!(attr(&label, "x").parse::<f64>().unwrap() < attr(moved, "x").parse::<f64>().unwrap());
+    assert_eq!(after.matches("<rect").count(), 2);  // the background and the train
+}
+
+#[test]
+fn empty_network_is_a_blank_picture() {
+    let svg = create_system().to_svg();
+    assert!(svg.contains("width=\"80\" height=\"80\""));
+    assert!(!svg.contains("<line"));
+}
@@ -0,0 +1,76 @@
+// switch_motion.rs
+//
+// Tests for the switch throw time: a switch in motion leads nowhere
+// and is protected by red signals until it comes to rest.
+//
+
+mod common;
+use common::{demo1, name, csv};
+
+use signaling::common::{END_B, SLOT_1};
+use signaling::common::JSwitch::{JSwitchLeft, JSwitchNone, JSwitchRight};
+use signaling::system::faults::Fault;
+
+#[test]
+fn thrown_at_once_by_default() {
+    let mut sys = demo1();
+    assert_eq!(sys.get_throw_ticks(), 0);
+    assert_eq!(sys.toggle_switch(&name("node005")), 0);
+    let node = sys.get_node(&name("node005")).unwrap();
+    assert!(node.get_switch_pos() == JSwitchRight);
+    assert!(!node.is_in_motion());
+}
+
+#[test]
+fn switch_in_motion_leads_nowhere() {
+    let mut sys = demo1();
+    sys.set_throw_ticks(2);
+    assert_eq!(sys.toggle_switch(&name("node005")), 0);
+    let node = sys.get_node(&name("node005")).unwrap();
+    assert!(node.is_in_motion());
+    assert!(node.get_switch_pos() == JSwitchNone);
+    assert!(node.get_next(SLOT_1).ee_edge.is_empty());
+    assert!(sys.get_edge(&name("tseg003")).unwrap().signals[END_B].signal_is_red());
+    assert_eq!(sys.toggle_switch(&name("node005")), 16);
+    assert_eq!(sys.set_clamped(&name("node005"), true), 16);
+
+    sys.step();
+    assert!(sys.get_node(&name("node005")).unwrap().is_in_motion());
+    sys.step();
+    let node = sys.get_node(&name("node005")).unwrap();
+    assert!(!node.is_in_motion());
+    assert!(node.get_switch_pos() == JSwitchRight);
+    assert!(!sys.get_edge(&name("tseg003")).unwrap().signals[END_B].signal_is_red());
+    assert!(csv(&sys).contains("\n0,switch,,,,node005,right\n"));
+    assert!(csv(&sys).contains("\n2,settled,,,,node005,right\n"));
+}
+
+#[test]
+fn train_waits_for_the_switch() {
+    let mut sys = demo1();
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &name("tseg003"), &name("tseg007")), 0);
+    sys.set_throw_ticks(2);
+    sys.toggle_switch(&name("node005"));
+    sys.run();
+    assert!(sys.get_train("train1").unwrap().has_arrived());
+    assert!(!sys.is_halted());
+    let log = csv(&sys);
+    assert!(log.contains("\n1,held,train1,tseg003,B,,\n"), "{log}");
+    assert!(log.contains("\n2,settled,,,,node005,right\n"), "{log}");
+    assert!(log.contains("\n2,entered,train1,tseg006,,,\n"), "{log}");
+}
+
+#[test]
+fn stuck_switch_stops_moving() {
+    let mut sys = demo1();
+    sys.set_throw_ticks(3);
+    sys.toggle_switch(&name("node005"));
+    let fault = Fault::SwitchStuck { node: name("node005"), position: JSwitchNone };
+    assert_eq!(sys.inject_fault(fault.clone()), 0);
+    assert!(!sys.get_node(&name("node005")).unwrap().is_in_motion());
+    sys.run();
+    assert!(sys.get_node(&name("node005")).unwrap().get_switch_pos() == JSwitchNone);
+    assert_eq!(sys.clear_fault(&fault), 0);
+    assert!(sys.get_node(&name("node005")).unwrap().get_switch_pos() == JSwitchLeft);
+}
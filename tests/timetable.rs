@@ -0,0 +1,190 @@
+// timetable.rs
+//
+// Tests for timetable driven dispatching: trains are placed when
+// their departure tick comes, routed through their calls, and the
+// report compares what happened with the timetable.
+//
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+mod common;
+use common::csv;
+
+use signaling::system::{create_system, System};
+
+fn open(file: &str) -> BufReader<File> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("data/{file}.txt"));
+    BufReader::new(File::open(path).unwrap())
+}
+
+fn demo1_with_timetable() -> System {
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut open("demo1")), 0);
+    assert_eq!(sys.load_timetable(&mut open("demo1_timetable")), 0);
+    sys
+}
+
+#[test]
+fn trains_depart_on_time() {
+    let mut sys = demo1_with_timetable();
+    assert_eq!(sys.get_timetable().len(), 3);
+    assert!(sys.get_train("express").is_none());
+
+    sys.step();
+    assert!(sys.get_train("express").is_some());
+    assert!(sys.get_train("local").is_none());
+    // Placed at the start of the step, then straight off.
+    sys.step();
+    assert_eq!(sys.get_train("local").unwrap().get_position().ee_edge, "tseg002");
+
+    // The run keeps going until the last service has departed.
+    sys.run();
+    assert_eq!(sys.get_tick(), 10);
+    for (train, edge) in [("express", "tseg005"), ("shuttle", "tseg006")] {
+        let tref = sys.get_train(train).unwrap();
+        assert_eq!(tref.get_position().ee_edge, edge);
+        assert!(tref.has_arrived());
+    }
+    // The express stands at its destination in the block beyond
+    // tseg003, so the local following it is held there for good.
+    let local = sys.get_train("local").unwrap();
+    assert_eq!(local.get_position().ee_edge, "tseg003");
+    assert!(!local.has_arrived());
+    let mut out = vec![];
+    sys.write_events_csv(&mut out);
+    assert!(String::from_utf8(out).unwrap().contains("\n10,held,local,tseg003,B,,\n"));
+}
+
+#[test]
+fn report_shows_lateness() {
+    let mut sys = demo1_with_timetable();
+    sys.run();
+    let report = sys.timetable_report();
+    assert_eq!(report.len(), 3);
+    let local = &report[1];
+    assert_eq!(local.train, "local");
+    let late: Vec<(&str, Option<i64>)> = local.calls.iter()
+        .map(|c| (c.kind, c.lateness()))
+        .collect();
+    assert_eq!(late, vec![("depart", Some(0)), ("stop", Some(0)), ("arrive", None)]);
+
+    assert_eq!(sys.format_timetable_report(), "\
+Train       Call    Segment      Sched  Actual  Late
+express     depart  tseg001          0       0     0
+            stop    tseg003          2       2     0
+            arrive  tseg005          4       4     0
+local       depart  tseg001          1       1     0
+            stop    tseg002          2       2     0
+            arrive  tseg004          4       -     -
+shuttle     depart  tseg007          8       8     0
+            arrive  tseg006          9       9     0
+");
+}
+
+#[test]
+fn blocked_entry_delays_departure() {
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut open("demo1")), 0);
+    let timetable = "\
+service: first,tseg005,0,tseg004
+service: second,tseg005,0,tseg003@3
+";
+    assert_eq!(sys.load_timetable(&mut timetable.as_bytes()), 0);
+    sys.run();
+    let report = sys.timetable_report();
+    assert_eq!(report[1].calls[0].actual, Some(1));
+    assert_eq!(report[1].calls[0].lateness(), Some(1));
+    assert_eq!(report[1].calls[1].actual, None);
+    assert_eq!(report[1].calls[1].lateness(), None);
+}
+
+#[test]
+fn service_that_cannot_depart_is_cancelled() {
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut open("demo1")), 0);
+    // The first train stays on the entry segment for good.
+    let timetable = "\
+service: first,tseg005,0,tseg005
+service: second,tseg005,1,tseg003
+";
+    assert_eq!(sys.load_timetable(&mut timetable.as_bytes()), 0);
+    sys.run();
+    assert!(sys.get_train("second").is_none());
+    assert_eq!(sys.timetable_report()[1].calls[0].actual, None);
+    let log = csv(&sys);
+    assert!(log.ends_with(&format!("{},cancelled,second,tseg005,,,\n", sys.get_tick())), "{log}");
+
+    // A further run has nothing left to dispatch.
+    sys.run();
+    assert_eq!(csv(&sys), log);
+}
+
+#[test]
+fn service_with_no_route_is_cancelled() {
+    let network = "\
+track: t1,1,n1,0,n2,0,sigA:N,sigB:N
+track: t2,1,n3,0,n4,0,sigA:N,sigB:N
+track: t3,1,n4,1,n5,0,sigA:N,sigB:N
+";
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut network.as_bytes()), 0);
+    let timetable = "\
+service: lost,t1,0,t2
+service: stray,t2,0,t1+1,t3
+service: home,t2,1,t3
+";
+    assert_eq!(sys.load_timetable(&mut timetable.as_bytes()), 0);
+    sys.run();
+    assert!(sys.get_train("lost").is_none());
+    assert!(sys.get_train("stray").is_none());
+    assert!(sys.get_train("home").unwrap().has_arrived());
+    assert!(sys.get_edge(&String::from("t1")).unwrap().train.is_empty());
+
+    let mut out = vec![];
+    sys.write_events_csv(&mut out);
+    let csv = String::from_utf8(out).unwrap();
+    assert!(csv.contains("\n0,cancelled,lost,t1,,,\n"), "{csv}");
+    assert_eq!(csv.matches(",cancelled,").count(), 2, "{csv}");
+    let report = sys.timetable_report();
+    assert_eq!(report[0].calls[0].actual, None);
+    assert_eq!(report[1].calls[0].actual, None);
+}
+
+#[test]
+fn route_passes_through_the_calls() {
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut open("demo3")), 0);
+    let via = [String::from("tseg005"), String::from("tseg009")];
+    let route = sys.find_route_via("tseg013", &via).unwrap();
+    let edges: Vec<&str> = route.iter().map(|p| p.ee_edge.as_str()).collect();
+    let stop = edges.iter().position(|e| *e == "tseg005").unwrap();
+    assert!(stop < edges.len() - 1);
+    assert_eq!(*edges.last().unwrap(), "tseg009");
+
+    // In demo1 the only way back from the junction's right fork is
+    // to reverse at the buffer stop beyond tseg007.
+    assert_eq!(sys.deserialize(&mut open("demo1")), 0);
+    let via = [String::from("tseg007"), String::from("tseg005")];
+    let route = sys.find_route_via("tseg001", &via).unwrap();
+    let edges: Vec<&str> = route.iter().map(|p| p.ee_edge.as_str()).collect();
+    assert!(edges.windows(2).any(|w| w == ["tseg007", "tseg007"]));
+    assert_eq!(*edges.last().unwrap(), "tseg005");
+}
+
+#[test]
+fn bad_timetables_are_rejected() {
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut open("demo1")), 0);
+    for text in ["service: t1,tseg001,0",
+                 "service: t1,tseg001,x,tseg005",
+                 "service: t1,tseg099,0,tseg005",
+                 "service: t1,tseg001,0,tseg005@soon",
+                 "train: t1,tseg001,0,tseg005"] {
+        assert_eq!(sys.load_timetable(&mut text.as_bytes()), 22, "\"{text}\" was accepted");
+    }
+    let twice = "service: t1,tseg001,0,tseg005\nservice: t1,tseg007,0,tseg006\n";
+    assert_eq!(sys.load_timetable(&mut twice.as_bytes()), 17);
+    assert!(sys.get_timetable().is_empty());
+}
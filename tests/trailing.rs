@@ -0,0 +1,105 @@
+// trailing.rs
+//
+// Tests for trailing-point moves through junctions: trailable
+// junctions are run through and their switch forced over, others
+// hold the train, and a train forced through a junction set against
+// it derails.
+//
+
+mod common;
+use common::{demo1, name, place, csv};
+
+use signaling::common::END_A;
+use signaling::common::JSwitch::JSwitchRight;
+use signaling::system::faults::Fault;
+use signaling::system::safety::Invariant;
+use signaling::system::create_system;
+
+#[test]
+fn held_at_a_switch_set_against() {
+    let mut sys = demo1();
+    place(&mut sys, "train1", "tseg006", "tseg001");
+    assert!(sys.get_edge(&name("tseg006")).unwrap().signals[END_A].signal_is_red());
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg006");
+}
+
+#[test]
+fn trailable_junction_is_run_through() {
+    let mut sys = demo1();
+    assert_eq!(sys.set_trailable(&name("node005"), true), 0);
+    place(&mut sys, "train1", "tseg006", "tseg001");
+    assert!(!sys.get_edge(&name("tseg006")).unwrap().signals[END_A].signal_is_red());
+    sys.run();
+    assert!(sys.get_train("train1").unwrap().has_arrived());
+    assert!(sys.get_node(&name("node005")).unwrap().get_switch_pos() == JSwitchRight);
+    assert!(!sys.is_halted());
+    let log = csv(&sys);
+    assert!(log.contains("\n1,trailed,train1,,,node005,\n1,entered,train1,tseg003,,,\n"), "{log}");
+
+    // Clamped, it holds its position.
+    sys.toggle_switch(&name("node005"));
+    sys.set_clamped(&name("node005"), true);
+    assert!(sys.find_route("tseg006", "tseg003").is_none());
+    assert!(sys.get_edge(&name("tseg006")).unwrap().signals[END_A].signal_is_red());
+}
+
+#[test]
+fn forced_through_a_switch_set_against() {
+    let mut sys = demo1();
+    place(&mut sys, "train1", "tseg006", "tseg001");
+    assert_eq!(sys.force_train("train1"), 0);
+
+    let train = sys.get_train("train1").unwrap();
+    assert_eq!(train.derailed_at.as_deref(), Some("node005"));
+    assert_eq!(train.get_position().ee_edge, "tseg006");
+    assert!(train.format(&sys).contains("Derailed: at junction \"node005\""));
+    assert!(csv(&sys).contains("\n0,derailed,train1,,,node005,\n"));
+    assert!(sys.is_halted());
+    let v = &sys.get_violations()[0];
+    assert_eq!(v.invariant, Invariant::Derailed);
+    assert_eq!(v.detail, "train1 came off the track at node005 from tseg006 with the switch set for tseg004");
+    assert_eq!(sys.step(), 0);
+}
+
+#[test]
+fn forced_past_a_dark_signal() {
+    let mut sys = demo1();
+    place(&mut sys, "train1", "tseg003", "tseg004");
+    sys.inject_fault(Fault::LampOut { segment: name("tseg003"), end: signaling::common::END_B });
+    sys.run();
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg003");
+    assert_eq!(sys.force_train("train1"), 0);
+    assert!(sys.get_train("train1").unwrap().has_arrived());
+    assert!(!sys.is_halted(), "{}", sys.format_safety_report());
+}
+
+#[test]
+fn forcing_is_refused() {
+    let mut sys = demo1();
+    assert_eq!(sys.force_train("nobody"), 2);
+    // A facing switch set the other way would send it off its route.
+    place(&mut sys, "train1", "tseg003", "tseg006");
+    assert_eq!(sys.force_train("train1"), 22);
+    place(&mut sys, "train2", "tseg006", "tseg006");
+    place(&mut sys, "train3", "tseg002", "tseg004");
+    assert_eq!(sys.force_train("train3"), 16);
+    assert!(!sys.is_halted());
+}
+
+#[test]
+fn trailable_junctions_are_saved() {
+    let mut sys = demo1();
+    sys.set_trailable(&name("node005"), true);
+    assert_eq!(sys.set_trailable(&name("node001"), true), 22);
+    assert_eq!(sys.set_trailable(&name("nowhere"), true), 2);
+    let mut out = vec![];
+    assert_eq!(sys.serialize(&mut out), 0);
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("\ntrailable: node005\n"), "{text}");
+
+    let mut copy = create_system();
+    assert_eq!(copy.deserialize(&mut text.as_bytes()), 0);
+    assert!(copy.get_node(&name("node005")).unwrap().trailable);
+    assert_ne!(copy.deserialize(&mut "track: t1,1,n1,0,n2,0,sigA:N,sigB:N\ntrailable: n1\n".as_bytes()), 0);
+}
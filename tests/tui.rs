@@ -0,0 +1,108 @@
+// tui.rs
+//
+// Tests for the terminal interface, drawn into an in-memory buffer and
+// driven with synthetic key presses.
+//
+#![cfg(feature = "tui")]
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::Color;
+use ratatui::Terminal;
+
+use signaling::system::{create_system, System};
+use signaling::tui::{draw, App};
+
+fn load_demo1() -> System {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/demo1.txt");
+    let mut sys = create_system();
+    assert_eq!(sys.deserialize(&mut BufReader::new(File::open(path).unwrap())), 0);
+    sys
+}
+
+fn render(sys: &System, app: &mut App) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|frame| draw(frame, sys, app)).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+fn lines(buffer: &Buffer) -> Vec<String> {
+    let area = buffer.area;
+    (0..area.height).map(|y| {
+        (0..area.width).map(|x| buffer[(x, y)].symbol().to_string()).collect()
+    }).collect()
+}
+
+// Returns the color of the first cell of `text` on the screen.
+fn color_of(buffer: &Buffer, text: &str) -> Color {
+    for (y, line) in lines(buffer).iter().enumerate() {
+        if let Some(ix) = line.find(text) {
+            // The border characters take more than one byte.
+            let x = line[..ix].chars().count();
+            return buffer[(x as u16, y as u16)].fg;
+        }
+    }
+    panic!("\"{text}\" is not on the screen");
+}
+
+// Returns the colors of the cells showing `symbol`.
+fn colors_of(buffer: &Buffer, symbol: &str) -> Vec<Color> {
+    buffer.content().iter().filter(|c| c.symbol() == symbol).map(|c| c.fg).collect()
+}
+
+fn press(sys: &mut System, app: &mut App, keys: &str) {
+    for c in keys.chars() {
+        let code = if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) };
+        app.handle_key(sys, KeyEvent::from(code));
+    }
+}
+
+#[test]
+fn schematic_shows_network() {
+    let sys = load_demo1();
+    let mut app = App::new();
+    let buffer = render(&sys, &mut app);
+    let screen = lines(&buffer).join("\n");
+
+    assert!(screen.contains("Network (7 track segments)"));
+    assert!(screen.contains("node005  left"));
+    // The segments are labelled where they are drawn: the forks of
+    // node005 either side of the main line, beyond tseg003.
+    let row = |text: &str| lines(&buffer).iter().position(|l| l.contains(text)).unwrap();
+    assert!(row("tseg004") < row("tseg003") && row("tseg003") < row("tseg006"));
+    assert!(row("tseg001") == row("tseg003"));
+    // tseg003 shows green toward tseg004, and tseg006 red at node005.
+    let signals = colors_of(&buffer, "\u{25cf}");
+    assert!(signals.contains(&Color::Green) && signals.contains(&Color::Red), "{signals:?}");
+    // The track, and the legs node005 is set for drawn over it.
+    assert!(buffer.content().iter().any(|c| c.fg == Color::DarkGray));
+    assert!(buffer.content().iter().any(|c| c.fg == Color::Cyan));
+}
+
+#[test]
+fn keys_toggle_place_and_step() {
+    let mut sys = load_demo1();
+    let mut app = App::new();
+
+    press(&mut sys, &mut app, "t");
+    let screen = lines(&render(&sys, &mut app)).join("\n");
+    assert!(screen.contains("node005  right"));
+    assert!(screen.contains("Toggled junction node005"));
+
+    press(&mut sys, &mut app, "ptseg001\ntseg007\n");
+    let buffer = render(&sys, &mut app);
+    let screen = lines(&buffer).join("\n");
+    assert!(screen.contains("Placed train1 on tseg001 bound for tseg007"));
+    assert!(screen.contains("[o==o]-[o==o]\\ train1"));
+    assert_eq!(color_of(&buffer, "[o==o]"), Color::Yellow);
+
+    press(&mut sys, &mut app, "sss");
+    assert_eq!(sys.get_train("train1").unwrap().get_position().ee_edge, "tseg006");
+    let screen = lines(&render(&sys, &mut app)).join("\n");
+    assert!(screen.contains("Step 3: 1 train(s) moved"));
+}
@@ -0,0 +1,106 @@
+// waypoints.rs
+//
+// Tests for trains routed through waypoints: the planner finds a
+// path through all of them in order, and the simulator holds the
+// train at each for its dwell time before it continues.
+//
+
+use signaling::system::events::Event;
+use signaling::system::train::Waypoint;
+use signaling::system::{create_system, System};
+
+fn line() -> System {
+    let (mut sys, text) = (create_system(), "\
+track: t1,1,n1,0,n2,0,sigA:N,sigB:N
+track: t2,1,n2,1,n3,0,sigA:N,sigB:N
+track: t3,1,n3,1,n4,0,sigA:N,sigB:N
+track: t4,1,n4,1,n5,0,sigA:N,sigB:N
+track: t5,1,n5,1,n6,0,sigA:N,sigB:N
+");
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &String::from("t1"), &String::from("t5")), 0);
+    sys
+}
+
+fn waypoint(segment: &str, dwell: u32) -> Waypoint {
+    Waypoint { segment: String::from(segment), dwell }
+}
+
+fn entered(sys: &System) -> Vec<(u32, String)> {
+    sys.get_events().iter().filter_map(|e| match &e.event {
+        Event::EnteredSegment { segment, .. } => Some((e.tick, segment.clone())),
+        _ => None,
+    }).collect()
+}
+
+#[test]
+fn parse_segment_and_dwell() {
+    assert_eq!(Waypoint::parse("tseg003"), Some(waypoint("tseg003", 0)));
+    assert_eq!(Waypoint::parse(" tseg003+4 "), Some(waypoint("tseg003", 4)));
+    assert_eq!(Waypoint::parse("tseg003+x"), None);
+    assert_eq!(Waypoint::parse("+2"), None);
+}
+
+#[test]
+fn train_dwells_at_each_waypoint() {
+    let mut sys = line();
+    assert_eq!(sys.set_train_waypoints("train1", vec![waypoint("t3", 2)]), 0);
+    sys.run();
+    assert_eq!(sys.get_tick(), 7);
+    let ticks: Vec<u32> = entered(&sys).iter().map(|(t, _)| *t).collect();
+    assert_eq!(ticks, vec![1, 2, 5, 6]);
+    let train = sys.get_train("train1").unwrap();
+    assert!(train.has_arrived());
+    assert!(!sys.has_dwelling_trains());
+}
+
+#[test]
+fn dwell_at_the_start_segment() {
+    let mut sys = line();
+    assert_eq!(sys.set_train_waypoints("train1", vec![waypoint("t1", 1)]), 0);
+    assert!(sys.has_dwelling_trains());
+    assert_eq!(sys.step(), 0);
+    assert_eq!(sys.step(), 1);
+    assert_eq!(entered(&sys), vec![(2, String::from("t2"))]);
+}
+
+#[test]
+fn unreachable_waypoint_has_no_route() {
+    let (mut sys, text) = (create_system(), "\
+track: t1,1,n1,0,n2,0,sigA:N,sigB:N
+track: t2,1,n2,1,n3,0,sigA:N,sigB:N
+track: u1,1,m1,0,m2,0,sigA:N,sigB:N
+");
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    sys.create_train("train1");
+    assert_eq!(sys.place_train("train1", &String::from("t1"), &String::from("t2")), 0);
+    // u1 is not connected to the line the train is on.
+    assert_ne!(sys.set_train_waypoints("train1", vec![waypoint("u1", 0)]), 0);
+    assert_eq!(sys.set_train_waypoints("train1", vec![waypoint("nowhere", 0)]), 2);
+    assert_eq!(sys.set_train_waypoints("train1", vec![waypoint("t1", 0)]), 0);
+}
+
+#[test]
+fn format_lists_waypoints() {
+    let mut sys = line();
+    sys.set_train_waypoints("train1", vec![waypoint("t2", 0), waypoint("t4", 3)]);
+    sys.step();
+    let text = sys.get_train("train1").unwrap().format(&sys);
+    assert!(text.contains("  Waypoints: t2 (passed), t4 (dwell 3)\n"), "{text}");
+}
+
+#[test]
+fn timetable_stops_dwell() {
+    let (mut sys, text) = (create_system(), "\
+track: t1,1,n1,0,n2,0,sigA:N,sigB:N
+track: t2,1,n2,1,n3,0,sigA:N,sigB:N
+track: t3,1,n3,1,n4,0,sigA:N,sigB:N
+");
+    assert_eq!(sys.deserialize(&mut text.as_bytes()), 0);
+    assert_eq!(sys.load_timetable(&mut "service: slow,t1,0,t2@1+3,t3@5\n".as_bytes()), 0);
+    assert_eq!(sys.get_timetable()[0].calls[0].dwell, 3);
+    sys.run();
+    let arrive = &sys.timetable_report()[0].calls[2];
+    assert_eq!((arrive.scheduled, arrive.actual), (Some(5), Some(5)));
+}